@@ -46,15 +46,29 @@ DEFINE TABLE declaration SCHEMALESS
         -- DEFINE FIELD product_weight ON declaration TYPE float
         --     ASSERT $value > 0;
         -- DEFINE FIELD product_description ON declaration TYPE string;
+        -- DEFINE FIELD goods ON declaration TYPE array;
+        -- DEFINE FIELD goods.* ON declaration TYPE object;
+        -- DEFINE FIELD currency ON declaration TYPE string;
         -- DEFINE FIELD transport_type ON declaration TYPE string;
         -- DEFINE FIELD transport_name ON declaration TYPE string;
+        -- DEFINE FIELD procedure ON declaration TYPE string;
         -- DEFINE FIELD sender_name ON declaration TYPE string;
         -- DEFINE FIELD receiver_name ON declaration TYPE string;
         -- DEFINE FIELD destination ON declaration TYPE string;
         -- DEFINE FIELD departure ON declaration TYPE string;
+        -- DEFINE FIELD registration_region ON declaration TYPE string;
+        -- DEFINE FIELD registration_number ON declaration TYPE object;
+        -- DEFINE FIELD priority ON declaration TYPE string;
+        -- DEFINE FIELD decision ON declaration TYPE object;
+        -- DEFINE FIELD revisions ON declaration TYPE array;
+        -- DEFINE FIELD revisions.* ON declaration TYPE object;
+        -- DEFINE FIELD attachments ON declaration TYPE array;
+        -- DEFINE FIELD attachments.* ON declaration TYPE object;
+        -- DEFINE FIELD signature ON declaration TYPE object;
         -- DEFINE FIELD state ON declaration TYPE string;
         -- DEFINE FIELD created_at ON declaration TYPE datetime;
         -- DEFINE FIELD updated_at ON declaration TYPE datetime;
+        -- DEFINE FIELD submitted_at ON declaration TYPE datetime;
         -- DEFINE INDEX idx_declaration ON declaration COLUMNS id UNIQUE;
 
 DEFINE TABLE customs SCHEMALESS
@@ -64,14 +78,19 @@ DEFINE TABLE customs SCHEMALESS
 
         -- DEFINE FIELD work_hours ON customs TYPE array;
         -- DEFINE FIELD location ON customs TYPE record(location);
-        -- DEFINE FIELD competence ON customs TYPE string;
+        -- DEFINE FIELD competence ON customs TYPE object;
         -- DEFINE FIELD phone_number ON customs TYPE string;
         -- DEFINE FIELD email ON customs TYPE string;
         -- DEFINE FIELD declarations ON customs TYPE array;
         -- DEFINE FIELD declarations.* ON customs TYPE record(declaration);
+        -- DEFINE FIELD capacity ON customs TYPE int;
+        -- DEFINE FIELD queue ON customs TYPE object;
         -- DEFINE FIELD inspectors ON customs TYPE array;
         -- DEFINE FIELD operators ON customs TYPE array;
         -- DEFINE FIELD customs_params ON customs TYPE object;
+        -- DEFINE FIELD registry ON customs TYPE object;
+        -- DEFINE FIELD require_signatures ON customs TYPE bool;
+        -- DEFINE FIELD trusted_keys ON customs TYPE object;
 
 DEFINE TABLE inspector SCHEMALESS
             PERMISSIONS
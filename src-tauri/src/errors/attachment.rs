@@ -0,0 +1,49 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum Err {
+    AttachmentNotFound {
+        declaration_id: Uuid,
+        attachment_id: Uuid,
+    },
+    BlobNotFound(String),
+    /// Stored content doesn't match its hash, i.e. file was damaged or replaced
+    HashMismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AttachmentNotFound {
+                declaration_id,
+                attachment_id,
+            } => {
+                write!(
+                    f,
+                    "Declaration has no such attachment. UUID = {declaration_id}, Attachment = {attachment_id}"
+                )
+            }
+            Self::BlobNotFound(hash) => {
+                write!(f, "Blob store has no content with hash: {hash}")
+            }
+            Self::HashMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Stored content is damaged. Expected hash = {expected}, Actual hash = {actual}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for Err {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -1,35 +1,34 @@
-// use std::error::Error;
-// use std::fmt::{Debug, Display, Formatter};
-//
-// use uuid::Uuid;
-//
-// #[derive(Debug)]
-// pub enum Err {
-//     NoOpenedChannel { customs_id: Uuid },
-//     ChannelWasClosed { customs_id: Uuid },
-//     FailedToReceive,
-//     FailedToSend,
-// }
-//
-// impl Display for Err {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         match self {
-//             Err::NoOpenedChannel { customs_id } => write!(
-//                 f,
-//                 "Customs (id={customs_id}) has no opened channel with Processor"
-//             ),
-//             Err::ChannelWasClosed { customs_id } => write!(
-//                 f,
-//                 "Customs (id={customs_id}) has it's channel with Processor closed"
-//             ),
-//             Err::FailedToReceive => todo!(),
-//             Err::FailedToSend => todo!(),
-//         }
-//     }
-// }
-//
-// impl Error for Err {
-//     fn source(&self) -> Option<&(dyn Error + 'static)> {
-//         None
-//     }
-// }
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+#[derive(Debug)]
+pub enum Err {
+    /// Processor actor has stopped, nobody reads the channel anymore
+    ChannelWasClosed,
+    /// Processor actor dropped the request without answering it
+    FailedToReceive,
+    /// Processor handled the request and failed with error of unknown type,
+    /// that can't cross threads, so only its message is kept
+    Remote(String),
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ChannelWasClosed => write!(f, "Channel with Processor is closed"),
+            Self::FailedToReceive => {
+                write!(
+                    f,
+                    "Processor didn't answer the request, reply channel dropped"
+                )
+            }
+            Self::Remote(message) => write!(f, "Processor failed to handle request: {message}"),
+        }
+    }
+}
+
+impl Error for Err {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
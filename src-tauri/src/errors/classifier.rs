@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+#[derive(Debug)]
+pub enum Err {
+    /// Code must be exactly 10 digits
+    InvalidLength(String),
+    NotNumeric(String),
+    UnknownCode(String),
+    /// Chapter or heading of the code is missing from classifier
+    MissingParent {
+        code: String,
+        parent: String,
+    },
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidLength(code) => {
+                write!(f, "Commodity code must have 10 digits. Code = {code}")
+            }
+            Self::NotNumeric(code) => {
+                write!(f, "Commodity code must contain digits only. Code = {code}")
+            }
+            Self::UnknownCode(code) => {
+                write!(f, "Commodity code is not in classifier. Code = {code}")
+            }
+            Self::MissingParent { code, parent } => {
+                write!(
+                    f,
+                    "Commodity code belongs to group, that is not in classifier. Code = {code}, Group = {parent}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for Err {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
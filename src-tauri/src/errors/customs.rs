@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum Err {
+    InspectorNotFound(Uuid),
+    /// Official customs code must be 8 digits
+    InvalidCode(String),
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InspectorNotFound(id) => {
+                write!(
+                    f,
+                    "Inspector with given uuid doesn't work at customs. UUID = {id}"
+                )
+            }
+            Self::InvalidCode(code) => {
+                write!(f, "Customs code must be 8 digits, got: {code}")
+            }
+        }
+    }
+}
+
+impl Error for Err {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -11,8 +11,17 @@ pub enum Err {
         field: String,
         value: String,
     },
-    DeclarationNotComplete(Uuid),
+    /// Every violation of validation rules, as `InvalidField`
+    Invalid(Uuid, Vec<Err>),
+    /// Inspector didn't ask to amend the field
+    FieldNotFlagged {
+        id: Uuid,
+        field: String,
+    },
     IncorrectState(Uuid, String),
+    /// Registration number doesn't match `customs code/ddmmyy/sequence` format
+    InvalidRegistrationNumber(String),
+    TemplateNotFound(Uuid),
 }
 
 impl Display for Err {
@@ -24,8 +33,25 @@ impl Display for Err {
             Self::InvalidField { id, field, value } => {
                 write!(f, "Field {field} has invalid value: {value}. UUID = {id}")
             }
-            Self::DeclarationNotComplete(id) => {
-                write!(f, "Declaration is not ready to be sent. UUID = {id}")
+            Self::Invalid(id, violations) => {
+                let fields: Vec<&str> = violations
+                    .iter()
+                    .filter_map(|violation| match violation {
+                        Self::InvalidField { field, .. } => Some(field.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "Declaration has invalid fields: {}. UUID = {id}",
+                    fields.join(", ")
+                )
+            }
+            Self::FieldNotFlagged { id, field } => {
+                write!(
+                    f,
+                    "Field {field} can't be amended, inspector didn't flag it. UUID = {id}"
+                )
             }
             Self::IncorrectState(id, state) => {
                 write!(
@@ -33,6 +59,12 @@ impl Display for Err {
                     "Declaration has invalid state. UUID = {id}, State = {state}"
                 )
             }
+            Self::InvalidRegistrationNumber(number) => {
+                write!(f, "Invalid registration number: {number}")
+            }
+            Self::TemplateNotFound(id) => {
+                write!(f, "The template with given uuid not found. UUID = {id}")
+            }
         }
     }
 }
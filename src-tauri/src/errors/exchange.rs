@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use chrono::NaiveDate;
+
+#[derive(Debug)]
+pub enum Err {
+    UnknownCurrency(String),
+    /// Currency is known, but has no rate set on or before the date
+    RateNotFound(String, NaiveDate),
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCurrency(currency) => {
+                write!(f, "Exchange rate table has no such currency: {currency}")
+            }
+            Self::RateNotFound(currency, date) => {
+                write!(
+                    f,
+                    "Exchange rate is not set at the date. Currency = {currency}, Date = {date}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for Err {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
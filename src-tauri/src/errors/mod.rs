@@ -1,6 +1,38 @@
+pub mod attachment;
 pub mod channel;
+pub mod classifier;
 pub mod client;
+pub mod customs;
 pub mod db;
 pub mod declaration;
+pub mod exchange;
 pub mod processor;
 pub mod representative;
+pub mod signature;
+
+use std::error::Error;
+
+/// Make error sendable between tasks, e.g. to answer a request to Processor actor.
+/// Errors of this crate keep their type, so receiver can still match them,
+/// the rest are replaced with their message
+pub fn into_send(err: Box<dyn Error>) -> Box<dyn Error + Send + Sync> {
+    keep::<processor::Err>(err)
+        .or_else(keep::<customs::Err>)
+        .or_else(keep::<declaration::Err>)
+        .or_else(keep::<signature::Err>)
+        .or_else(keep::<attachment::Err>)
+        .or_else(keep::<classifier::Err>)
+        .or_else(keep::<exchange::Err>)
+        .or_else(keep::<channel::Err>)
+        .or_else(keep::<client::Err>)
+        .or_else(keep::<representative::Err>)
+        .or_else(keep::<db::Err>)
+        .unwrap_or_else(|err| Box::new(channel::Err::Remote(err.to_string())))
+}
+
+fn keep<T: Error + Send + Sync + 'static>(
+    err: Box<dyn Error>,
+) -> Result<Box<dyn Error + Send + Sync>, Box<dyn Error>> {
+    err.downcast::<T>()
+        .map(|err| err as Box<dyn Error + Send + Sync>)
+}
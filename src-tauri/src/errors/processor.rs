@@ -6,6 +6,13 @@ use uuid::Uuid;
 #[derive(Debug)]
 pub enum Err {
     CannotBorrowCustoms(Uuid),
+    NoCompetentCustoms(Uuid),
+    NoSuitableCustoms(Uuid),
+    CustomsNotFound(Uuid),
+    CustomsAtCapacity(Uuid),
+    NotForwarded(Uuid),
+    /// Competent customs are closed, while declaration has to be processed right away
+    NoOpenCustoms(Uuid),
 }
 
 impl Display for Err {
@@ -14,6 +21,39 @@ impl Display for Err {
             Self::CannotBorrowCustoms(id) => {
                 write!(f, "Can't borrow customs mutabaly, probably already borrowed or customs doesn' exist. Uuid = {id}")
             }
+            Self::NoCompetentCustoms(id) => {
+                write!(
+                    f,
+                    "None of connected customs is competent to process the declaration. UUID = {id}"
+                )
+            }
+            Self::NoSuitableCustoms(id) => {
+                write!(
+                    f,
+                    "Dispatch strategy rejected every available customs. UUID = {id}"
+                )
+            }
+            Self::CustomsNotFound(id) => {
+                write!(f, "Customs with given uuid is not connected. UUID = {id}")
+            }
+            Self::NotForwarded(id) => {
+                write!(
+                    f,
+                    "Neither connected customs nor peer processors could take the declaration. UUID = {id}"
+                )
+            }
+            Self::NoOpenCustoms(id) => {
+                write!(
+                    f,
+                    "None of competent customs is open at the moment. UUID = {id}"
+                )
+            }
+            Self::CustomsAtCapacity(id) => {
+                write!(
+                    f,
+                    "Every competent customs has reached its capacity. UUID = {id}"
+                )
+            }
         }
     }
 }
@@ -7,6 +7,7 @@ use uuid::Uuid;
 pub enum Err {
     ServiceNotFound(Uuid),
     ClientNotFound(Uuid),
+    ClientReadLocked(Uuid),
     ClientWriteLocked(Uuid),
 }
 
@@ -22,6 +23,9 @@ impl Display for Err {
             Self::ClientNotFound(id) => {
                 write!(f, "Client with given uuid not found. UUID = {id}")
             }
+            Self::ClientReadLocked(id) => {
+                write!(f, "Can't lock client to read from. UUID = {id}")
+            }
             Self::ClientWriteLocked(id) => {
                 write!(f, "Can't lock client to write into. UUID = {id}")
             }
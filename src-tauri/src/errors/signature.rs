@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum Err {
+    NotSigned(Uuid),
+    /// Signature doesn't match declaration, i.e. declaration was changed after signing
+    InvalidSignature(Uuid),
+    /// Declaration is signed by someone else, than its owner
+    SignerMismatch {
+        id: Uuid,
+        signer: Uuid,
+    },
+    /// Key of the signer differs from the one customs knows
+    UntrustedKey {
+        id: Uuid,
+        signer: Uuid,
+    },
+}
+
+impl Display for Err {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSigned(id) => {
+                write!(f, "Declaration is not signed. UUID = {id}")
+            }
+            Self::InvalidSignature(id) => {
+                write!(
+                    f,
+                    "Signature doesn't match declaration content. UUID = {id}"
+                )
+            }
+            Self::SignerMismatch { id, signer } => {
+                write!(
+                    f,
+                    "Declaration is signed not by its owner. UUID = {id}, Signer = {signer}"
+                )
+            }
+            Self::UntrustedKey { id, signer } => {
+                write!(
+                    f,
+                    "Declaration is signed with unknown key. UUID = {id}, Signer = {signer}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for Err {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
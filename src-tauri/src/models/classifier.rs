@@ -0,0 +1,161 @@
+//! Commodity classifier (TN VED), i.e. official list of commodity codes and their descriptions
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use tokio::sync::OnceCell;
+
+use crate::errors::classifier::Err as ClErr;
+use crate::prelude::*;
+
+/// Classifier declarations are validated against, see `Classifier::init`
+static SHARED: OnceCell<Arc<Classifier>> = OnceCell::const_new();
+/// Full commodity code length
+const CODE_LENGTH: usize = 10;
+/// Lengths of chapter, heading, subheading and subheading of the Union
+const GROUP_LENGTHS: [usize; 4] = [2, 4, 6, 8];
+/// Every code must belong to a known chapter and heading, subheadings are optional
+const REQUIRED_GROUPS: [usize; 2] = [2, 4];
+
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Classifier {
+    /// Codes of groups and goods mapped to their descriptions
+    entries: BTreeMap<String, String>,
+}
+
+impl Classifier {
+    pub async fn new(entries: BTreeMap<String, String>) -> Self {
+        Self { entries }
+    }
+
+    /// Classifier from JSON object, that maps codes to descriptions
+    pub async fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(serde_json::from_str(json)?).await)
+    }
+
+    /// Load classifier from local data file, e.g. bundled `resources/tnved.json`
+    #[tracing::instrument(skip(path))]
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        tracing::info!("Loading classifier from {}", path.display());
+        let classifier = Self::from_json(&tokio::fs::read_to_string(path).await?).await?;
+        tracing::info!("Classifier loaded: {} entries", classifier.entries.len());
+
+        Ok(classifier)
+    }
+
+    /// Load classifier `Declaration::validate` checks commodity codes against.
+    /// It is loaded once, later calls give the same classifier whatever the path
+    pub async fn init(path: impl AsRef<Path>) -> Result<Arc<Self>, Box<dyn Error>> {
+        SHARED
+            .get_or_try_init(|| async { Ok(Arc::new(Self::load(path).await?)) })
+            .await
+            .cloned()
+    }
+
+    /// Classifier loaded by `init`, None until it is
+    pub async fn shared() -> Option<Arc<Self>> {
+        SHARED.get().cloned()
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use super::*;
+
+    impl super::Classifier {
+        /// Checks length, existence and groups of the code, returns its official description
+        pub async fn check(&self, code: &str) -> Result<&str, ClErr> {
+            if !code.chars().all(|c| c.is_ascii_digit()) {
+                return Err(ClErr::NotNumeric(code.to_string()));
+            }
+            if code.len() != CODE_LENGTH {
+                return Err(ClErr::InvalidLength(code.to_string()));
+            }
+            for len in REQUIRED_GROUPS {
+                let parent = &code[..len];
+                if !self.entries.contains_key(parent) {
+                    return Err(ClErr::MissingParent {
+                        code: code.to_string(),
+                        parent: parent.to_string(),
+                    });
+                }
+            }
+
+            self.entries
+                .get(code)
+                .map(String::as_str)
+                .ok_or_else(|| ClErr::UnknownCode(code.to_string()))
+        }
+
+        /// Official description of code or group
+        pub async fn describe(&self, code: &str) -> Option<&str> {
+            self.entries.get(code).map(String::as_str)
+        }
+
+        /// Known groups of the code with their descriptions, chapter goes first
+        pub async fn groups(&self, code: &str) -> Vec<(&str, &str)> {
+            GROUP_LENGTHS
+                .iter()
+                .filter(|len| **len < code.len() && code.is_char_boundary(**len))
+                .filter_map(|len| self.entries.get_key_value(&code[..*len]))
+                .map(|(code, description)| (code.as_str(), description.as_str()))
+                .collect()
+        }
+    }
+}
+
+/// Boilerplate
+impl Classifier {
+    getter_ref!( { async } entries: &BTreeMap<String, String>);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Classifier;
+    use crate::errors::classifier::Err as ClErr;
+
+    const PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tnved.json");
+
+    #[tokio::test]
+    async fn check() {
+        let classifier = Classifier::load(PATH).await.unwrap();
+        assert_eq!(
+            classifier.check("0101210000").await.unwrap(),
+            "Horses, pure-bred breeding animals"
+        );
+        assert!(matches!(
+            classifier.check("0101").await,
+            Err(ClErr::InvalidLength(_))
+        ));
+        assert!(matches!(
+            classifier.check("01012100AB").await,
+            Err(ClErr::NotNumeric(_))
+        ));
+        assert!(matches!(
+            classifier.check("0101210001").await,
+            Err(ClErr::UnknownCode(_))
+        ));
+        assert!(matches!(
+            classifier.check("9999999999").await,
+            Err(ClErr::MissingParent { parent, .. }) if parent == "99"
+        ));
+
+        let groups = classifier.groups("0406105000").await;
+        let codes: Vec<&str> = groups.iter().map(|(code, _)| *code).collect();
+        assert_eq!(codes, ["04", "0406", "040610"]);
+    }
+
+    #[tokio::test]
+    async fn load() {
+        let classifier = Classifier::load(PATH).await.unwrap();
+        assert_eq!(classifier.describe("01").await, Some("Live animals"));
+        assert!(Classifier::load("missing.json").await.is_err());
+    }
+}
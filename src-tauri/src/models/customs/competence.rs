@@ -0,0 +1,108 @@
+//! Competence of customs, i.e. which declarations customs is allowed to process
+
+use crate::prelude::*;
+
+/// Empty list means that customs has no restrictions on the given criterion
+#[derive(Clone, Default, Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct Competence {
+    /// Groups of product codes (code prefixes), e.g. "01" for live animals
+    product_groups: Vec<String>,
+    transport_types: Vec<String>,
+    procedures: Vec<String>,
+}
+
+impl Competence {
+    pub async fn new(
+        product_groups: &[&str],
+        transport_types: &[&str],
+        procedures: &[&str],
+    ) -> Self {
+        Self {
+            product_groups: product_groups.iter().map(ToString::to_string).collect(),
+            transport_types: transport_types.iter().map(ToString::to_string).collect(),
+            procedures: procedures.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use crate::models::declaration::Declaration;
+
+    impl super::Competence {
+        /// Checks product code group of every goods line, transport type and procedure of declaration
+        pub async fn covers<State>(&self, decl: &Declaration<State>) -> bool {
+            let mut codes = Vec::new();
+            for item in decl.items().await {
+                codes.push(item.code_ref().await.to_string());
+            }
+            let transport = decl.transport_type_ref().await;
+            let procedure = decl.procedure_ref().await;
+
+            (self.product_groups.is_empty()
+                || codes.iter().all(|code| {
+                    self.product_groups
+                        .iter()
+                        .any(|group| code.starts_with(group.as_str()))
+                }))
+                && (self.transport_types.is_empty()
+                    || self
+                        .transport_types
+                        .iter()
+                        .any(|kind| kind.eq_ignore_ascii_case(transport)))
+                && (self.procedures.is_empty()
+                    || self.procedures.iter().any(|proc| proc == procedure))
+        }
+    }
+}
+
+/// Boilerplate
+impl Competence {
+    getter_ref!( { async } product_groups: &Vec<String>, { async } transport_types: &Vec<String>, { async } procedures: &Vec<String>);
+    getter_mut!( { async } product_groups: &mut Vec<String>, { async } transport_types: &mut Vec<String>, { async } procedures: &mut Vec<String>);
+    setter!( { async } product_groups: Vec<String>, { async } transport_types: Vec<String>, { async } procedures: Vec<String>);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::declaration::{Declaration, Pending};
+
+    #[tokio::test]
+    async fn covers() {
+        let mut decl = Declaration::<Pending>::default();
+        decl.set_product_code("0102290000")
+            .await
+            .set_transport_type("Air")
+            .await
+            .set_procedure("IM40")
+            .await;
+
+        assert!(Competence::default().covers(&decl).await);
+        assert!(
+            Competence::new(&["01"], &["air"], &[])
+                .await
+                .covers(&decl)
+                .await
+        );
+        assert!(!Competence::new(&["02"], &[], &[]).await.covers(&decl).await);
+        assert!(
+            !Competence::new(&[], &["Sea"], &[])
+                .await
+                .covers(&decl)
+                .await
+        );
+        assert!(
+            !Competence::new(&[], &[], &["EX10"])
+                .await
+                .covers(&decl)
+                .await
+        );
+    }
+}
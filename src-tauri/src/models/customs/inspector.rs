@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
-    models::declaration::{Declaration, Inspecting},
+    models::declaration::{Declaration, InfoRequested, Inspecting},
     prelude::*,
     utils::HasId,
 };
@@ -15,6 +15,9 @@ pub struct Inspector {
     rank: String,
     post: String,
     declarations: HashMap<Uuid, Declaration<Inspecting>>,
+    /// Declarations, that wait for declarant to amend them
+    #[serde(default)]
+    awaiting: HashMap<Uuid, Declaration<InfoRequested>>,
 }
 
 impl Inspector {
@@ -29,6 +32,7 @@ impl Inspector {
             post: post.to_string(),
             rank: rank.to_string(),
             declarations: HashMap::default(),
+            awaiting: HashMap::default(),
         }
     }
 }
@@ -40,22 +44,30 @@ impl Inspector {
 ///
 /// Import Logic: ``use <path>::<struct>::logic::*;``
 ///
-mod logic {
+pub mod logic {
     use std::error::Error;
 
     use futures::stream;
     use futures::StreamExt;
     use uuid::Uuid;
 
+    use crate::errors::attachment::Err as AErr;
     use crate::errors::declaration::Err as PErr;
+    use crate::errors::into_send;
     use crate::models::customs::CustomsParams;
+    use crate::models::declaration::Approved;
+    use crate::models::declaration::Decision;
     use crate::models::declaration::Declaration;
     use crate::models::declaration::DeclarationGeneric;
+    use crate::models::declaration::InfoRequest;
+    use crate::models::declaration::InfoRequested;
     use crate::models::declaration::Inspecting;
     use crate::models::declaration::Pending;
+    use crate::models::declaration::Rejected;
     use crate::models::declaration::Tax;
+    use crate::models::participants::Participant;
     use crate::models::processor::logic::Logic as PLogic;
-    use crate::models::processor::Processor;
+    use crate::repository::blob_store::BlobStore;
 
     pub trait Logic {
         async fn get_declaration(&self, id: &Uuid) -> Option<&Declaration<Inspecting>>;
@@ -69,13 +81,53 @@ mod logic {
             declaration: &Declaration<Inspecting>,
             declaration_corrected: &Declaration<Inspecting>,
             conf: CustomsParams,
-        ) -> Tax;
+        ) -> Result<Tax, Box<dyn Error>>;
         async fn remove_declaration(&mut self, id: &Uuid) -> Option<Declaration<Inspecting>>;
-        async fn reprocess(
+        /// Send declaration back to Processor, or a handle to it, for another dispatch
+        async fn reprocess<P: PLogic>(
             &mut self,
-            processor: &mut Processor,
+            processor: &mut P,
             id: &Uuid,
         ) -> Result<(), Box<dyn Error>>;
+        /// Take declaration out of the pool and send it, approved, to its owner
+        async fn approve<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: &Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Approved>, Box<dyn Error>>;
+        /// Take declaration out of the pool and send it, rejected, to its owner.
+        /// Owner must be told why, so reason can't be empty
+        async fn reject<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: &Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Rejected>, Box<dyn Error>>;
+        /// Take declaration out of the pool and ask its owner to amend the given fields
+        async fn request_info<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: &Uuid,
+            fields: &[&str],
+            message: &str,
+        ) -> Result<Declaration<InfoRequested>, Box<dyn Error>>;
+        /// Content of declaration attachment. Inspector sees attachments only
+        /// while declaration is being inspected
+        async fn get_attachment(
+            &self,
+            id: &Uuid,
+            attachment_id: &Uuid,
+            store: &BlobStore,
+        ) -> Result<Vec<u8>, Box<dyn Error>>;
+        /// Declaration, that waits for its owner to amend it
+        async fn get_awaiting(&self, id: &Uuid) -> Option<&Declaration<InfoRequested>>;
+        /// Take amended declaration back into the pool. Only inspector,
+        /// who requested information, accepts it, and only if flagged fields were changed
+        async fn resubmit(
+            &mut self,
+            declaration: Declaration<InfoRequested>,
+        ) -> Result<Declaration<Inspecting>, Box<dyn Error>>;
     }
 
     impl Logic for super::Inspector {
@@ -90,8 +142,15 @@ mod logic {
 
         #[tracing::instrument]
         async fn fetch_declaration(&mut self, declaration: Declaration<Pending>) {
+            let previous = declaration.clone();
             let mut declaration: Declaration<Inspecting> = declaration.into();
-            declaration.set_inspected_by(Some(self.id)).await;
+            declaration
+                .revise(self.id, &previous)
+                .await
+                .set_inspected_by(Some(self.id))
+                .await
+                .set_state_entered_at(chrono::Utc::now())
+                .await;
             tracing::info!(
                 "Declarartion {} state changed to Inspecting",
                 declaration.id().await
@@ -104,20 +163,26 @@ mod logic {
             &mut self,
             mut declaration: Declaration<Inspecting>,
         ) -> Option<Declaration<Inspecting>> {
+            let id = declaration.id().await;
+            if let Some(old) = self.declarations.get(&id) {
+                declaration.revise(self.id, old).await;
+            }
             declaration.set_inspected_by(Some(self.id)).await;
             tracing::info!("Declarartion {} value(-s) changed", declaration.id().await);
             self.declarations
                 .insert(declaration.id().await, declaration)
         }
 
-        async fn reprocess(
+        async fn reprocess<P: PLogic>(
             &mut self,
-            processor: &mut Processor,
+            processor: &mut P,
             id: &Uuid,
         ) -> Result<(), Box<dyn Error>> {
             let declaration = self.remove_declaration(id).await;
             if let Some(declaration) = declaration {
-                let declaration: Declaration<Pending> = declaration.into();
+                let previous = declaration.clone();
+                let mut declaration: Declaration<Pending> = declaration.into();
+                declaration.revise(self.id, &previous).await;
                 tracing::info!("Reprocessing declaration {}", id);
                 processor.process_declaration(&declaration).await?;
                 Ok(())
@@ -127,18 +192,176 @@ mod logic {
             }
         }
 
+        #[tracing::instrument(skip(owner))]
+        async fn approve<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: &Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Approved>, Box<dyn Error>> {
+            let declaration = self.decide(owner, id, Some(reason)).await?;
+            let mut approved: Declaration<Approved> = declaration.clone().into();
+            approved.revise(self.id, &declaration).await;
+            self.deliver(
+                owner,
+                &DeclarationGeneric::Approved(approved.clone()),
+                declaration,
+            )
+            .await?;
+            tracing::info!("Declaration {} approved", id);
+
+            Ok(approved)
+        }
+
+        #[tracing::instrument(skip(owner))]
+        async fn reject<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: &Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Rejected>, Box<dyn Error>> {
+            if reason.trim().is_empty() {
+                return Err(Box::new(PErr::InvalidField {
+                    id: *id,
+                    field: "reason".to_string(),
+                    value: reason.to_string(),
+                }));
+            }
+            let declaration = self.decide(owner, id, Some(reason)).await?;
+            let mut rejected: Declaration<Rejected> = declaration.clone().into();
+            rejected.revise(self.id, &declaration).await;
+            self.deliver(
+                owner,
+                &DeclarationGeneric::Rejected(rejected.clone()),
+                declaration,
+            )
+            .await?;
+            tracing::info!("Declaration {} rejected: {}", id, reason);
+
+            Ok(rejected)
+        }
+
+        #[tracing::instrument(skip(owner))]
+        async fn request_info<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: &Uuid,
+            fields: &[&str],
+            message: &str,
+        ) -> Result<Declaration<InfoRequested>, Box<dyn Error>> {
+            if fields.is_empty() {
+                return Err(Box::new(PErr::InvalidField {
+                    id: *id,
+                    field: "fields".to_string(),
+                    value: String::new(),
+                }));
+            }
+            let mut declaration = self.decide(owner, id, None).await?;
+            declaration
+                .set_info_request(Some(InfoRequest::new(self.id, fields, message).await))
+                .await;
+            let mut requested: Declaration<InfoRequested> = declaration.clone().into();
+            requested.revise(self.id, &declaration).await;
+            self.deliver(
+                owner,
+                &DeclarationGeneric::InfoRequested(requested.clone()),
+                declaration,
+            )
+            .await?;
+            self.awaiting.insert(*id, requested.clone());
+            tracing::info!("Information on declaration {} requested: {:?}", id, fields);
+
+            Ok(requested)
+        }
+
+        #[tracing::instrument(skip(store))]
+        async fn get_attachment(
+            &self,
+            id: &Uuid,
+            attachment_id: &Uuid,
+            store: &BlobStore,
+        ) -> Result<Vec<u8>, Box<dyn Error>> {
+            let declaration = self
+                .declarations
+                .get(id)
+                .ok_or(PErr::DeclarationNotFound(*id))?;
+            let attachment =
+                declaration
+                    .attachment(attachment_id)
+                    .await
+                    .ok_or(AErr::AttachmentNotFound {
+                        declaration_id: *id,
+                        attachment_id: *attachment_id,
+                    })?;
+
+            store.get(attachment.hash_ref().await).await
+        }
+
+        async fn get_awaiting(&self, id: &Uuid) -> Option<&Declaration<InfoRequested>> {
+            self.awaiting.get(id)
+        }
+
+        #[tracing::instrument]
+        async fn resubmit(
+            &mut self,
+            declaration: Declaration<InfoRequested>,
+        ) -> Result<Declaration<Inspecting>, Box<dyn Error>> {
+            let id = declaration.id().await;
+            let Some(requested) = self.awaiting.get(&id) else {
+                tracing::error!("Inspector {} doesn't wait for declaration {}", self.id, id);
+                return Err(Box::new(PErr::DeclarationNotFound(id)));
+            };
+            let flagged = match requested.info_request_ref().await {
+                Some(request) => request.fields_ref().await.clone(),
+                None => Vec::new(),
+            };
+            for change in requested.diff(&declaration).await {
+                let field = change.field_ref().await;
+                if !flagged.iter().any(|name| name == field) {
+                    tracing::error!("Declaration {} has unflagged field {} changed", id, field);
+                    return Err(Box::new(PErr::FieldNotFlagged {
+                        id,
+                        field: field.to_string(),
+                    }));
+                }
+            }
+            // Declaration signed once has to stay signed over its amended content
+            if requested.signature_ref().await.is_some()
+                || declaration.signature_ref().await.is_some()
+            {
+                declaration.verify_signature().await?;
+            }
+            let requested = self
+                .awaiting
+                .remove(&id)
+                .ok_or(PErr::DeclarationNotFound(id))?;
+            let author = declaration.signed_by().await;
+            let mut declaration: Declaration<Inspecting> = declaration.into();
+            declaration
+                .revise(author, &requested)
+                .await
+                .set_info_request(None)
+                .await
+                .set_inspected_by(Some(self.id))
+                .await
+                .set_state_entered_at(chrono::Utc::now())
+                .await;
+            self.declarations.insert(id, declaration.clone());
+            tracing::info!("Amended declaration {} is back to inspection", id);
+
+            Ok(declaration)
+        }
+
         #[tracing::instrument]
         async fn calc_tax(
             &self,
             declaration_old: &Declaration<Inspecting>,
             declaration_corrected: &Declaration<Inspecting>,
             conf: CustomsParams,
-        ) -> Tax {
-            let fee = conf.fee;
-            let fee_per_item = fee
-                .calculate_fee(declaration_corrected.product_price().await)
-                .await;
-            // Check fields of declaration
+        ) -> Result<Tax, Box<dyn Error>> {
+            let customs_value = declaration_corrected.customs_value(&conf.rates).await?;
+            let fee_per_item = conf.fee.calculate_fee(customs_value).await;
+            // Check fields of declaration and its goods lines
             // if fields has changed, then calculate tax
             let mut tax = Tax::new();
             tax.set_inspector_id(self.id).await;
@@ -146,70 +369,117 @@ mod logic {
                 .await;
             tax.set_receiver_id(declaration_corrected.signed_by().await)
                 .await;
-            let (mut incorrect_fields, mut price) = (0, 0.0);
-            for (old, new) in declaration_old
-                .fields()
-                .await
-                .0
-                .iter()
-                .zip(declaration_corrected.fields().await.0.iter())
-            {
-                if old != new {
-                    incorrect_fields += 1;
-                    price += fee_per_item;
-                }
-            }
-
-            for (old, new) in declaration_old
-                .fields()
-                .await
-                .1
-                .iter()
-                .zip(declaration_corrected.fields().await.1.iter())
-            {
-                if (old - new).abs() > f64::EPSILON {
-                    incorrect_fields += 1;
-                    price += fee_per_item;
-                }
-            }
-
-            for (old, new) in declaration_old
-                .fields()
-                .await
-                .2
+            let (old_strings, old_numbers) = declaration_old.fields().await;
+            let (new_strings, new_numbers) = declaration_corrected.fields().await;
+            let mut incorrect_fields = old_strings
                 .iter()
-                .zip(declaration_corrected.fields().await.2.iter())
-            {
-                if old != new {
-                    incorrect_fields += 1;
-                    price += fee_per_item;
-                }
-            }
+                .zip(new_strings.iter())
+                .filter(|(old, new)| old != new)
+                .count()
+                + old_numbers
+                    .iter()
+                    .zip(new_numbers.iter())
+                    .filter(|(old, new)| (*old - *new).abs() > f64::EPSILON)
+                    .count();
+            // Every field of added or removed goods line is incorrect
+            incorrect_fields += old_strings.len().max(new_strings.len())
+                - old_strings.len().min(new_strings.len())
+                + old_numbers.len().max(new_numbers.len())
+                - old_numbers.len().min(new_numbers.len());
+            let price = fee_per_item * incorrect_fields as f64;
             tax.set_incorrect_fields(incorrect_fields)
                 .await
                 .set_price(price)
                 .await;
             tracing::info!("Tax calculated: {:?}", tax);
 
-            tax
+            Ok(tax)
+        }
+    }
+
+    /// Private methods
+    impl super::Inspector {
+        /// Take declaration out of the pool, with decision recorded on it, if there is reason
+        async fn decide<P: Participant>(
+            &mut self,
+            owner: &P,
+            id: &Uuid,
+            reason: Option<&str>,
+        ) -> Result<Declaration<Inspecting>, Box<dyn Error>> {
+            if owner.get_declaration(*id).await.is_none() {
+                tracing::error!("Declaration {} doesn't belong to the participant", id);
+                return Err(Box::new(PErr::DeclarationNotFound(*id)));
+            }
+            let mut declaration = self
+                .declarations
+                .remove(id)
+                .ok_or(PErr::DeclarationNotFound(*id))?;
+            if let Some(reason) = reason {
+                declaration
+                    .set_decision(Some(Decision::new(self.id, reason).await))
+                    .await;
+            }
+
+            Ok(declaration)
+        }
+
+        /// Send decided declaration to its owner. If owner doesn't take it,
+        /// declaration goes back to the pool undecided and without information requested.
+        /// Holds no `Box<dyn Error>` across awaits, so Processor can run as an actor
+        async fn deliver<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            decided: &DeclarationGeneric,
+            mut declaration: Declaration<Inspecting>,
+        ) -> Result<(), Box<dyn Error>> {
+            let delivered = owner.update_declaration(decided).await.map_err(into_send);
+            if let Err(err) = delivered {
+                tracing::error!(
+                    "Decision on declaration {} wasn't delivered: {}",
+                    declaration.id().await,
+                    err
+                );
+                declaration
+                    .set_decision(None)
+                    .await
+                    .set_info_request(None)
+                    .await;
+                self.declarations
+                    .insert(declaration.id().await, declaration);
+                return Err(err);
+            }
+
+            Ok(())
         }
     }
 }
 
 /// Boilerplate
 impl Inspector {
-    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } rank: &str, { async } post: &str, { async } declarations: &HashMap<Uuid, Declaration<Inspecting>>);
+    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } rank: &str, { async } post: &str, { async } declarations: &HashMap<Uuid, Declaration<Inspecting>>, { async } awaiting: &HashMap<Uuid, Declaration<InfoRequested>>);
     getter_mut!(  { async } id: &mut Uuid, { async } name: &mut String, { async } rank: &mut String, { async } post: &mut String, { async } declarations: &mut HashMap<Uuid, Declaration<Inspecting>>);
     setter!( { async } id: Uuid, { async } name: &str, { async } rank: &str, { async } post: &str, { async } declarations: HashMap<Uuid, Declaration<Inspecting>>);
     getter!( { async } id: Uuid);
 }
 
+impl HasId for Inspector {
+    fn id(&mut self) -> &mut Uuid {
+        &mut self.id
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::logic::Logic;
     use super::*;
     use crate::models::{
         customs::{CustomsParams, Fee},
-        declaration::{Declaration, Pending},
+        declaration::{
+            Declaration, DeclarationGeneric, GenericDowncast, InfoRequested, Pending, Rejected,
+        },
+        exchange::ExchangeRates,
+        misc::attachment::AttachmentKind,
+        participants::{declarant::Declarant, Participant},
     };
     #[tokio::test]
     async fn get_declaration() {
@@ -231,6 +501,236 @@ mod tests {
         assert!(declaration.is_some());
     }
 
+    #[tokio::test]
+    async fn approve_reject() {
+        let mut inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut declarant = Declarant::new("John").await;
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let declaration: Declaration<Pending> = Declaration::new().await.into();
+            ids.push(declaration.id().await);
+            declarant
+                .update_declaration(&DeclarationGeneric::Pending(declaration.clone()))
+                .await
+                .unwrap();
+            inspector.fetch_declaration(declaration).await;
+        }
+
+        let approved = inspector
+            .approve(&mut declarant, &ids[0], "Documents are correct")
+            .await
+            .unwrap();
+        let decision = approved.decision_ref().await.clone().unwrap();
+        assert_eq!(decision.inspector_id().await, inspector.id().await);
+        assert_eq!(decision.reason_ref().await, "Documents are correct");
+        assert!(inspector.get_declaration(&ids[0]).await.is_none());
+        assert_eq!(
+            declarant.get_declaration(ids[0]).await,
+            Some(&DeclarationGeneric::Approved(approved))
+        );
+
+        // Reason is required and declaration stays in the pool without it
+        assert!(inspector
+            .reject(&mut declarant, &ids[1], " ")
+            .await
+            .is_err());
+        let mut stranger = Declarant::new("Jane").await;
+        assert!(inspector
+            .reject(&mut stranger, &ids[1], "Wrong code")
+            .await
+            .is_err());
+        assert!(inspector.get_declaration(&ids[1]).await.is_some());
+
+        inspector
+            .reject(&mut declarant, &ids[1], "Wrong code")
+            .await
+            .unwrap();
+        let rejected = declarant.get_declaration(ids[1]).await.unwrap();
+        let rejected: &Declaration<Rejected> = rejected.downcast().unwrap();
+        assert_eq!(
+            rejected
+                .decision_ref()
+                .await
+                .as_ref()
+                .unwrap()
+                .reason_ref()
+                .await,
+            "Wrong code"
+        );
+        assert!(inspector
+            .approve(&mut declarant, &ids[1], "")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn request_info() {
+        let mut inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut other = Inspector::new("Petr", "Inspector", "Leutenant").await;
+        let mut declarant = Declarant::new("John").await;
+        let declaration: Declaration<Pending> = Declaration::new().await.into();
+        let id = declaration.id().await;
+        declarant
+            .update_declaration(&DeclarationGeneric::Pending(declaration.clone()))
+            .await
+            .unwrap();
+        inspector.fetch_declaration(declaration).await;
+
+        assert!(inspector
+            .request_info(&mut declarant, &id, &[], "Nothing")
+            .await
+            .is_err());
+        inspector
+            .request_info(&mut declarant, &id, &["goods[0].code"], "Code is wrong")
+            .await
+            .unwrap();
+        assert!(inspector.get_declaration(&id).await.is_none());
+        assert!(inspector.get_awaiting(&id).await.is_some());
+
+        // Only flagged fields can be amended
+        assert!(declarant
+            .amend_declaration(id, "sender_name", "Jane")
+            .await
+            .is_err());
+        declarant
+            .amend_declaration(id, "goods[0].code", "0406105000")
+            .await
+            .unwrap();
+        // Declaration goes back to the inspector, who asked for information
+        let owned: &Declaration<InfoRequested> = declarant
+            .get_declaration(id)
+            .await
+            .unwrap()
+            .downcast()
+            .unwrap();
+        let owned = owned.clone();
+        assert!(other.resubmit(owned.clone()).await.is_err());
+        let mut sneaky = inspector.get_awaiting(&id).await.unwrap().clone();
+        sneaky.set_destination("Elsewhere").await;
+        assert!(inspector.resubmit(sneaky).await.is_err());
+        inspector.resubmit(owned.clone()).await.unwrap();
+
+        let amended = inspector.get_declaration(&id).await.unwrap();
+        assert_eq!(amended.items().await[0].code_ref().await, "0406105000");
+        assert!(amended.info_request_ref().await.is_none());
+        let revision = amended.revisions_of("goods[0].code").await[0];
+        assert_eq!(revision.from_state_ref().await, "InfoRequested");
+        assert_eq!(revision.to_state_ref().await, "Inspecting");
+        assert!(inspector.get_awaiting(&id).await.is_none());
+        assert!(inspector.resubmit(owned).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn request_info_signed() {
+        let mut inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut declarant = Declarant::new("John").await;
+        let keys = crate::models::misc::signature::KeyPair::generate().await;
+        declarant.set_keys(Some(keys.clone())).await;
+        let mut draft = Declaration::new().await;
+        draft.sign(declarant.id().await, &keys).await;
+        let declaration: Declaration<Pending> = draft.into();
+        let id = declaration.id().await;
+        declarant
+            .update_declaration(&DeclarationGeneric::Pending(declaration.clone()))
+            .await
+            .unwrap();
+        inspector.fetch_declaration(declaration).await;
+        inspector
+            .request_info(&mut declarant, &id, &["goods[0].code"], "Code is wrong")
+            .await
+            .unwrap();
+
+        // Amended content has to be signed anew
+        let mut unsigned = inspector.get_awaiting(&id).await.unwrap().clone();
+        unsigned.amend("goods[0].code", "0406105000").await.unwrap();
+        assert!(unsigned.verify_signature().await.is_err());
+        assert!(inspector.resubmit(unsigned).await.is_err());
+        declarant
+            .amend_declaration(id, "goods[0].code", "0406105000")
+            .await
+            .unwrap();
+        let signed: &Declaration<InfoRequested> = declarant
+            .get_declaration(id)
+            .await
+            .unwrap()
+            .downcast()
+            .unwrap();
+        inspector.resubmit(signed.clone()).await.unwrap();
+        let amended = inspector.get_declaration(&id).await.unwrap();
+        assert!(amended.verify_signature().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn get_attachment() {
+        let root = std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4()));
+        let store = crate::repository::blob_store::BlobStore::open(&root)
+            .await
+            .unwrap();
+        let mut inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut draft = Declaration::new().await;
+        let invoice = draft
+            .attach(
+                &store,
+                AttachmentKind::Invoice,
+                "invoice.pdf",
+                "application/pdf",
+                b"Invoice #1",
+            )
+            .await
+            .unwrap();
+        assert_eq!(invoice.size().await, 10);
+        let id = draft.id().await;
+        let declaration: Declaration<Pending> = draft.into();
+
+        // Not inspected yet
+        assert!(inspector
+            .get_attachment(&id, invoice.id_ref().await, &store)
+            .await
+            .is_err());
+        inspector.fetch_declaration(declaration).await;
+        assert_eq!(
+            inspector
+                .get_attachment(&id, invoice.id_ref().await, &store)
+                .await
+                .unwrap(),
+            b"Invoice #1"
+        );
+        assert!(inspector
+            .get_attachment(&id, &Uuid::new_v4(), &store)
+            .await
+            .is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn update_declaration_revision() {
+        let mut inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let declaration: Declaration<Pending> = Declaration::new().await.into();
+        let id = declaration.id().await;
+        inspector.fetch_declaration(declaration).await;
+
+        let mut corrected = inspector.get_declaration(&id).await.unwrap().clone();
+        corrected.set_product_code("0406105000").await;
+        // Corrected copy can't rewrite the history
+        corrected.restore_revisions(Vec::new()).await;
+        inspector.update_declaration(corrected).await;
+
+        let revisions = inspector
+            .get_declaration(&id)
+            .await
+            .unwrap()
+            .revisions_ref()
+            .await
+            .clone();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].to_state_ref().await, "Inspecting");
+        assert_eq!(revisions[1].author().await, inspector.id().await);
+        let change = &revisions[1].changes_ref().await[0];
+        assert_eq!(change.field_ref().await, "goods[0].code");
+        assert_eq!(change.new_ref().await, "0406105000");
+    }
+
     #[tokio::test]
     async fn calc_tax() {
         let declaration_old: Declaration<Pending> = Declaration::new().await.into();
@@ -246,7 +746,8 @@ mod tests {
                 &declaration_corrected,
                 customs_params.clone(),
             )
-            .await;
+            .await
+            .unwrap();
         assert_eq!(tax.incorrect_fields().await, 0);
         assert!(tax.price().await < f64::EPSILON);
         declaration_old
@@ -264,14 +765,58 @@ mod tests {
             .set_product_code("234")
             .await;
         let tax = inspector
-            .calc_tax(&declaration_old, &declaration_corrected, customs_params)
-            .await;
+            .calc_tax(
+                &declaration_old,
+                &declaration_corrected,
+                customs_params.clone(),
+            )
+            .await
+            .unwrap();
         assert_eq!(tax.incorrect_fields().await, 2);
         assert!((tax.price().await - 20.0).abs() < f64::EPSILON);
-    }
-}
-impl HasId for Inspector {
-    fn id(&mut self) -> &mut Uuid {
-        &mut self.id
+
+        // Fee is taken from value in national currency
+        let submitted_at = declaration_corrected.created_at().await;
+        customs_params.fee = Fee::Percentage(0.1);
+        customs_params
+            .rates
+            .set_rate("USD", submitted_at.date_naive(), 80.0)
+            .await;
+        declaration_corrected
+            .set_currency("USD")
+            .await
+            .set_submitted_at(Some(submitted_at))
+            .await;
+        let tax = inspector
+            .calc_tax(
+                &declaration_old,
+                &declaration_corrected,
+                customs_params.clone(),
+            )
+            .await
+            .unwrap();
+        assert!((tax.price().await - 2.0 * 0.1 * 200.0 * 80.0).abs() < 1e-6);
+
+        // Customs converts at bundled rates, if given them
+        let bundled_params = CustomsParams {
+            fee: Fee::Percentage(0.1),
+            ..CustomsParams::new().await.unwrap()
+        };
+        let may = chrono::NaiveDate::from_ymd_opt(2023, 5, 15)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc();
+        declaration_corrected.set_submitted_at(Some(may)).await;
+        let tax = inspector
+            .calc_tax(&declaration_old, &declaration_corrected, bundled_params)
+            .await
+            .unwrap();
+        assert!((tax.price().await - 2.0 * 0.1 * 200.0 * 80.52).abs() < 1e-6);
+        customs_params.rates = ExchangeRates::default();
+        assert!(inspector
+            .calc_tax(&declaration_old, &declaration_corrected, customs_params)
+            .await
+            .is_err());
     }
 }
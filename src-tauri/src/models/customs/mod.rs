@@ -1,14 +1,19 @@
 use std::collections::HashMap;
 
-use self::{inspector::Inspector, operator::Operator};
+use self::{competence::Competence, inspector::Inspector, operator::Operator};
 
 use super::{
-    declaration::{Declaration, Pending},
-    misc::location::Location,
+    declaration::{Billing, Declaration, Pending, Tax},
+    exchange::ExchangeRates,
+    misc::{
+        location::Location, queue::DeclarationQueue, registration::Registry, signature::PublicKey,
+    },
 };
+use crate::errors::signature::Err as SErr;
 use crate::{prelude::*, utils::HasId};
 use chrono::naive::NaiveTime;
 use uuid::Uuid;
+pub mod competence;
 pub mod inspector;
 pub mod operator;
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -25,14 +30,15 @@ impl Default for Fee {
 }
 
 impl Fee {
-    pub async fn calculate_fee(&self, product_price: f64) -> f64 {
+    /// Customs value must be in national currency, see `Declaration::customs_value`
+    pub async fn calculate_fee(&self, customs_value: f64) -> f64 {
         match self {
-            Self::Percentage(perc) => perc * product_price,
+            Self::Percentage(perc) => perc * customs_value,
             Self::Flat(flat_tax) => *flat_tax,
             Self::ProgressiveFlat { border, fee } => {
                 let mut calc_fee = 0.0;
                 for (&border, &fee) in border.iter().zip(fee.iter()) {
-                    if product_price < border {
+                    if customs_value < border {
                         calc_fee = fee;
                     }
                 }
@@ -50,6 +56,20 @@ pub struct CustomsParams {
     banned_export_products: Vec<String>,
     banned_import_origin: Vec<String>,
     banned_export_origin: Vec<String>,
+    /// Rates customs values are converted at before calculating fee.
+    /// Empty by default, so only declarations without currency are taxed, see `CustomsParams::new`
+    #[serde(default)]
+    rates: ExchangeRates,
+}
+
+impl CustomsParams {
+    /// No fee and bans, bundled exchange rates
+    pub async fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            rates: ExchangeRates::bundled().await?,
+            ..Default::default()
+        })
+    }
 }
 
 // #[derive(Clone, PartialEq, PartialOrd, Debug)]
@@ -60,13 +80,36 @@ pub struct Customs {
     work_hours: Option<(NaiveTime, NaiveTime)>,
     name: Option<String>,
     location: Option<Location>,
-    competence: Option<String>,
+    competence: Option<Competence>,
     phone_number: Option<String>,
     email: Option<String>,
     declarations: HashMap<Uuid, Declaration<Pending>>,
+    /// Maximum number of pending declarations, None for no limit
+    #[serde(default)]
+    capacity: Option<usize>,
+    /// Order in which pending declarations are handed to inspectors
+    #[serde(default)]
+    queue: DeclarationQueue,
     inspectors: HashMap<Uuid, Inspector>,
     operators: HashMap<Uuid, Operator>,
     customs_params: CustomsParams,
+    /// Issues registration numbers of accepted declarations. Customs without official
+    /// code doesn't register declarations
+    #[serde(default)]
+    registry: Option<Registry>,
+    /// Whether unsigned declarations and ones signed with keys customs doesn't trust
+    /// are refused. Signed ones are always verified
+    #[serde(default)]
+    require_signatures: bool,
+    /// Known public keys of participants, declarations signed by them with other keys are refused
+    #[serde(default)]
+    trusted_keys: HashMap<Uuid, PublicKey>,
+    /// Taxes received, by their id. Declaration each belongs to may have already left customs
+    #[serde(default)]
+    taxes: HashMap<Uuid, Tax>,
+    /// Billings received, by their id
+    #[serde(default)]
+    billings: HashMap<Uuid, Billing>,
 }
 
 impl Customs {
@@ -99,9 +142,14 @@ impl Customs {
 /// Import Logic: ``use <path>::<struct>::logic::*;``
 ///
 pub mod logic {
+    use chrono::{DateTime, Duration, Utc};
+
+    use crate::errors::customs::Err as CErr;
     use crate::errors::declaration::Err as DErr;
+    use crate::models::customs::inspector::logic::Logic as InspectorLogic;
     use crate::models::declaration::Declaration;
     use crate::models::declaration::{DeclarationGeneric, Document, Pending};
+    use crate::models::misc::registration::RegistrationNumber;
     use futures::stream;
     use futures::StreamExt;
     use std::error::Error;
@@ -109,26 +157,78 @@ pub mod logic {
 
     pub trait Logic {
         /// Takes declaration copy, updates declarations, if there is any,
-        /// otherwise - add it to the pool
+        /// otherwise - add it to the pool. Time declaration entered the pool is kept on update.
+        /// Declaration without registration number of this customs is registered by customs,
+        /// so number issued by customs declaration was relocated from is replaced.
+        /// Signature of declaration is verified, see `require_signatures` and `trusted_keys`
         async fn update_decl(
             &mut self,
             decl: Declaration<Pending>,
         ) -> Result<Option<Declaration<Pending>>, Box<dyn Error>>;
-        /// Gives declaration reference with provided UUID, if there is any
+        /// Gives declaration copy with provided UUID, if there is any, either pending,
+        /// being inspected or waiting for amendment
         async fn get_declaration(&self, id: &Uuid) -> Option<DeclarationGeneric>;
-        // async fn receive_docs(&mut self, doc: Document) -> Result<(), Box<dyn Error>>;
+        /// Id of declaration with the given registration number, wherever it is in customs
+        async fn find_by_number(&self, number: &RegistrationNumber) -> Option<Uuid>;
+        /// Inspector, who is inspecting declaration with provided UUID or waits for its amendment
+        async fn inspector_of(&self, id: &Uuid) -> Option<Uuid>;
+        /// Ids of every declaration customs holds, pending, being inspected and waiting for amendment
+        async fn declaration_ids(&self) -> Vec<Uuid>;
+        /// Takes any document. Pending declaration is updated as by `update_decl`,
+        /// declarations in other states are refused. Billings and taxes are kept
+        /// along with declaration they belong to, see `documents_of`, ones of declarations
+        /// customs doesn't hold are refused
+        async fn receive_docs(&mut self, doc: Document) -> Result<(), Box<dyn Error>>;
+        /// Billings and taxes, that belong to declaration with provided UUID
+        async fn documents_of(&self, declaration_id: &Uuid) -> Vec<Document>;
         /// Gives declaration reference with provided UUID, if there is any, and deletes it from
         /// the pool
         async fn remove_declaration(&mut self, id: &Uuid) -> Option<Declaration<Pending>>;
+        /// Takes the most urgent pending declaration out of the pool
+        async fn next_declaration(&mut self) -> Option<Declaration<Pending>>;
+        /// Hands the most urgent pending declaration over to inspector.
+        /// Returns its id, or None, if there is nothing to inspect
+        async fn assign_next(
+            &mut self,
+            inspector_id: &Uuid,
+        ) -> Result<Option<Uuid>, Box<dyn Error>>;
+        /// Checks whether declaration falls under customs competence.
+        /// Customs without declared competence accepts any declaration
+        async fn is_competent(&self, decl: &Declaration<Pending>) -> bool;
+        /// Time left until customs opens, evaluated in customs local time.
+        /// Zero, if customs is open at the given moment or has no work hours set
+        async fn opens_in(&self, at: DateTime<Utc>) -> Duration;
+        async fn is_open(&self, at: DateTime<Utc>) -> bool;
+        /// Pending declarations per inspector. Customs without inspectors is considered
+        /// infinitely loaded
+        async fn load(&self) -> f64;
+        /// Whether customs has reached its capacity and can't take more declarations
+        async fn is_full(&self) -> bool;
     }
 
     impl Logic for super::Customs {
         async fn update_decl(
             &mut self,
-            decl: Declaration<Pending>,
+            mut decl: Declaration<Pending>,
         ) -> Result<Option<Declaration<Pending>>, Box<dyn Error>> {
             let id = decl.id().await;
             tracing::info!("Updating declaration with id: {}", id);
+            self.check_signature(&decl).await?;
+            let entered_at = match self.declarations.get(&id) {
+                Some(old_decl) => old_decl.state_entered_at().await,
+                None => Utc::now(),
+            };
+            decl.set_state_entered_at(entered_at).await;
+            let registered_here = match (decl.registration_number_ref().await, &self.registry) {
+                (Some(number), Some(registry)) => {
+                    number.customs_code_ref().await == registry.customs_code_ref().await
+                }
+                _ => false,
+            };
+            if !registered_here {
+                self.register(&mut decl).await;
+            }
+            self.queue.push(id, decl.priority().await).await;
             let old_decl = self.declarations.insert(id, decl);
             if old_decl.is_some() {
                 tracing::info!("Declaration with id: {} was updated", id);
@@ -139,18 +239,260 @@ pub mod logic {
             Ok(old_decl)
         }
 
+        #[tracing::instrument(skip(self))]
+        async fn receive_docs(&mut self, doc: Document) -> Result<(), Box<dyn Error>> {
+            let declaration_id = doc.declaration_id().await;
+            if !matches!(doc, Document::Declaration(_))
+                && !self.declaration_ids().await.contains(&declaration_id)
+            {
+                tracing::warn!("Document of unknown declaration {}", declaration_id);
+                return Err(Box::new(DErr::DeclarationNotFound(declaration_id)));
+            }
+            match doc {
+                Document::Declaration(DeclarationGeneric::Pending(decl)) => {
+                    self.update_decl(decl).await?;
+                }
+                Document::Declaration(decl) => {
+                    let id = decl.id().await;
+                    tracing::warn!("Customs takes only pending declarations, got {}", id);
+                    return Err(Box::new(DErr::IncorrectState(
+                        id,
+                        "Not Pending".to_string(),
+                    )));
+                }
+                Document::Billing(billing) => {
+                    tracing::info!(
+                        "Billing {} received for declaration {}",
+                        billing.id_ref().await,
+                        billing.declaration_id_ref().await
+                    );
+                    self.billings.insert(billing.id().await, billing);
+                }
+                Document::Tax(tax) => {
+                    tracing::info!(
+                        "Tax {} received for declaration {}",
+                        tax.id_ref().await,
+                        tax.declaration_id_ref().await
+                    );
+                    self.taxes.insert(tax.id().await, tax);
+                }
+            }
+
+            Ok(())
+        }
+
+        async fn documents_of(&self, declaration_id: &Uuid) -> Vec<Document> {
+            let mut docs = Vec::new();
+            for billing in self.billings.values() {
+                if billing.declaration_id_ref().await == declaration_id {
+                    docs.push(Document::Billing(billing.clone()));
+                }
+            }
+            for tax in self.taxes.values() {
+                if tax.declaration_id_ref().await == declaration_id {
+                    docs.push(Document::Tax(tax.clone()));
+                }
+            }
+
+            docs
+        }
+
         async fn get_declaration(&self, id: &Uuid) -> Option<DeclarationGeneric> {
             if let Some(decl) = self.declarations.get(id) {
-                Some(DeclarationGeneric::Pending(decl.clone()))
-            } else {
-                tracing::warn!("No declaration with id: {}", id);
-                None
+                return Some(DeclarationGeneric::Pending(decl.clone()));
+            }
+            for inspector in self.inspectors.values() {
+                if let Some(decl) = inspector.get_declaration(id).await {
+                    return Some(DeclarationGeneric::Inspecting(decl.clone()));
+                }
+                if let Some(decl) = inspector.get_awaiting(id).await {
+                    return Some(DeclarationGeneric::InfoRequested(decl.clone()));
+                }
             }
+            tracing::warn!("No declaration with id: {}", id);
+
+            None
+        }
+
+        async fn find_by_number(&self, number: &RegistrationNumber) -> Option<Uuid> {
+            for id in self.declaration_ids().await {
+                let registered = match self.get_declaration(&id).await? {
+                    DeclarationGeneric::Pending(decl) => decl.registration_number().await,
+                    DeclarationGeneric::Inspecting(decl) => decl.registration_number().await,
+                    DeclarationGeneric::InfoRequested(decl) => decl.registration_number().await,
+                    _ => None,
+                };
+                if registered.as_ref() == Some(number) {
+                    return Some(id);
+                }
+            }
+
+            None
+        }
+
+        async fn inspector_of(&self, id: &Uuid) -> Option<Uuid> {
+            for (inspector_id, inspector) in &self.inspectors {
+                if inspector.declarations_ref().await.contains_key(id)
+                    || inspector.awaiting_ref().await.contains_key(id)
+                {
+                    return Some(*inspector_id);
+                }
+            }
+
+            None
+        }
+
+        async fn declaration_ids(&self) -> Vec<Uuid> {
+            let mut ids: Vec<Uuid> = self.declarations.keys().copied().collect();
+            for inspector in self.inspectors.values() {
+                ids.extend(inspector.declarations_ref().await.keys().copied());
+                ids.extend(inspector.awaiting_ref().await.keys().copied());
+            }
+
+            ids
         }
 
         async fn remove_declaration(&mut self, id: &Uuid) -> Option<Declaration<Pending>> {
+            self.queue.remove(id).await;
             self.declarations.remove(id)
         }
+
+        async fn next_declaration(&mut self) -> Option<Declaration<Pending>> {
+            // Declarations put in through `declarations_mut` or `set_declarations`
+            // aren't queued yet, and ones taken out through them are stale in queue
+            for (id, decl) in &self.declarations {
+                if !self.queue.contains(id).await {
+                    self.queue.push(*id, decl.priority().await).await;
+                }
+            }
+            while let Some(id) = self.queue.pop().await {
+                if let Some(decl) = self.declarations.remove(&id) {
+                    return Some(decl);
+                }
+            }
+
+            None
+        }
+
+        async fn assign_next(
+            &mut self,
+            inspector_id: &Uuid,
+        ) -> Result<Option<Uuid>, Box<dyn Error>> {
+            if !self.inspectors.contains_key(inspector_id) {
+                return Err(Box::new(CErr::InspectorNotFound(*inspector_id)));
+            }
+            let Some(decl) = self.next_declaration().await else {
+                return Ok(None);
+            };
+            let id = decl.id().await;
+            if let Some(inspector) = self.inspectors.get_mut(inspector_id) {
+                tracing::info!("Declaration {} assigned to inspector {}", id, inspector_id);
+                inspector.fetch_declaration(decl).await;
+            }
+
+            Ok(Some(id))
+        }
+
+        async fn is_competent(&self, decl: &Declaration<Pending>) -> bool {
+            match &self.competence {
+                Some(competence) => competence.covers(decl).await,
+                None => true,
+            }
+        }
+
+        async fn opens_in(&self, at: DateTime<Utc>) -> Duration {
+            let Some((open, close)) = self.work_hours else {
+                return Duration::zero();
+            };
+            let now = match &self.location {
+                Some(location) => location.local_time(at).await,
+                None => at.time(),
+            };
+            let is_open = match open.cmp(&close) {
+                std::cmp::Ordering::Less => open <= now && now < close,
+                // Night shift, e.g. 20:00 - 08:00
+                std::cmp::Ordering::Greater => open <= now || now < close,
+                std::cmp::Ordering::Equal => true,
+            };
+            if is_open {
+                return Duration::zero();
+            }
+
+            let wait = open - now;
+            if wait < Duration::zero() {
+                wait + Duration::days(1)
+            } else {
+                wait
+            }
+        }
+
+        async fn is_open(&self, at: DateTime<Utc>) -> bool {
+            self.opens_in(at).await.is_zero()
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        async fn load(&self) -> f64 {
+            if self.inspectors.is_empty() {
+                return f64::INFINITY;
+            }
+
+            self.declarations.len() as f64 / self.inspectors.len() as f64
+        }
+
+        async fn is_full(&self) -> bool {
+            self.capacity
+                .map_or(false, |capacity| self.declarations.len() >= capacity)
+        }
+    }
+}
+
+/// Private methods
+impl Customs {
+    async fn check_signature(&self, decl: &Declaration<Pending>) -> Result<(), SErr> {
+        let id = decl.id().await;
+        let Some(signature) = decl.signature_ref().await else {
+            return if self.require_signatures {
+                Err(SErr::NotSigned(id))
+            } else {
+                Ok(())
+            };
+        };
+        decl.verify_signature().await?;
+        let signer = signature.signer().await;
+        // Signature carries its own key, so only a trusted key proves who the signer is
+        match self.trusted_keys.get(&signer) {
+            Some(key) if key == signature.public_key_ref().await => Ok(()),
+            None if !self.require_signatures => Ok(()),
+            _ => {
+                tracing::warn!("Declaration {} is signed with unknown key", id);
+                Err(SErr::UntrustedKey { id, signer })
+            }
+        }
+    }
+
+    /// Assign the next registration number of customs local day to declaration
+    async fn register(&mut self, decl: &mut Declaration<Pending>) {
+        let now = chrono::Utc::now();
+        let date = match &self.location {
+            Some(location) => location.local_date(now).await,
+            None => now.date_naive(),
+        };
+        let Some(registry) = self.registry.as_mut() else {
+            tracing::warn!(
+                "Customs {} has no code, declaration is not registered",
+                self.id
+            );
+            // Number of other customs would make `find_by_number` resolve here
+            decl.set_registration_number(None).await;
+            return;
+        };
+        let number = registry.issue(date).await;
+        tracing::info!(
+            "Declaration {} registered as {}",
+            decl.id_ref().await,
+            number
+        );
+        decl.set_registration_number(Some(number)).await;
     }
 }
 
@@ -158,42 +500,59 @@ pub mod logic {
 impl Customs {
     getter_ref!(
         { async } id: &Uuid,
+        { async } work_hours: &Option<(NaiveTime, NaiveTime)>,
         { async } name: &Option<String>,
-        { async } competence: &Option<String>,
+        { async } location: &Option<Location>,
+        { async } competence: &Option<Competence>,
         { async } phone_number: &Option<String>,
         { async } email: &Option<String>,
         { async } declarations: &HashMap<Uuid, Declaration<Pending>>,
+        { async } capacity: &Option<usize>,
+        { async } queue: &DeclarationQueue,
         { async } inspectors: &HashMap<Uuid, Inspector>,
-        { async } operators: &HashMap<Uuid, Operator>
+        { async } operators: &HashMap<Uuid, Operator>,
+        { async } registry: &Option<Registry>,
+        { async } trusted_keys: &HashMap<Uuid, PublicKey>,
+        { async } taxes: &HashMap<Uuid, Tax>,
+        { async } billings: &HashMap<Uuid, Billing>
     );
 
     setter!(
         { async } id: Uuid,
+        { async } work_hours: Option<(NaiveTime, NaiveTime)>,
         { async } name: Option<String>,
-        { async } competence: Option<String>,
+        { async } location: Option<Location>,
+        { async } competence: Option<Competence>,
         { async } phone_number: Option<String>,
         { async } email: Option<String>,
         { async } declarations: HashMap<Uuid, Declaration<Pending>>,
+        { async } capacity: Option<usize>,
         { async } inspectors: HashMap<Uuid, Inspector>,
-        { async } operators: HashMap<Uuid, Operator>
+        { async } operators: HashMap<Uuid, Operator>,
+        { async } registry: Option<Registry>,
+        { async } require_signatures: bool,
+        { async } trusted_keys: HashMap<Uuid, PublicKey>
     );
 
     getter_mut!(
         { async } name: &mut Option<String>,
-        { async } competence: &mut Option<String>,
+        { async } competence: &mut Option<Competence>,
         { async } phone_number: &mut Option<String>,
         { async } email: &mut Option<String>,
         { async } declarations: &mut HashMap<Uuid, Declaration<Pending>>,
         { async } inspectors: &mut HashMap<Uuid, Inspector>,
-        { async } operators: &mut HashMap<Uuid, Operator>
+        { async } operators: &mut HashMap<Uuid, Operator>,
+        { async } trusted_keys: &mut HashMap<Uuid, PublicKey>
     );
 
     getter!(
         { async } id: Uuid,
         { async } name: Option<String>,
-        { async } competence: Option<String>,
+        { async } competence: Option<Competence>,
         { async } phone_number: Option<String>,
-        { async } email: Option<String>
+        { async } email: Option<String>,
+        { async } capacity: Option<usize>,
+        { async } require_signatures: bool
     );
 }
 
@@ -202,3 +561,223 @@ impl HasId for Customs {
         &mut self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::logic::Logic;
+    use super::*;
+    use crate::models::declaration::{DeclarationGeneric, Document, Draft, Priority};
+    use crate::models::misc::registration::RegistrationNumber;
+    use crate::models::misc::signature::KeyPair;
+
+    #[tokio::test]
+    async fn assign_next() {
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        let inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let inspector_id = inspector.id().await;
+        assert!(customs.assign_next(&inspector_id).await.is_err());
+        customs
+            .inspectors_mut()
+            .await
+            .insert(inspector_id, inspector);
+
+        let regular: Declaration<Pending> = Declaration::new().await.into();
+        let mut humanitarian: Declaration<Pending> = Declaration::new().await.into();
+        humanitarian.set_priority(Priority::Humanitarian).await;
+        customs.update_decl(regular.clone()).await.unwrap();
+        customs.update_decl(humanitarian.clone()).await.unwrap();
+
+        assert_eq!(
+            customs.assign_next(&inspector_id).await.unwrap(),
+            Some(humanitarian.id().await)
+        );
+        assert!(customs.inspectors_ref().await[&inspector_id]
+            .declarations_ref()
+            .await
+            .contains_key(humanitarian.id_ref().await));
+        let next = customs.next_declaration().await.unwrap();
+        assert_eq!(next.id().await, regular.id().await);
+        assert_eq!(customs.assign_next(&inspector_id).await.unwrap(), None);
+        assert!(customs.declarations_ref().await.is_empty());
+
+        // Declarations changed bypassing `update_decl` are still handed out in order
+        customs
+            .declarations_mut()
+            .await
+            .insert(regular.id().await, regular.clone());
+        customs
+            .set_declarations(HashMap::from([(
+                humanitarian.id().await,
+                humanitarian.clone(),
+            )]))
+            .await;
+        let next = customs.next_declaration().await.unwrap();
+        assert_eq!(next.id().await, humanitarian.id().await);
+        assert!(customs.next_declaration().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn receive_docs() {
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        let id = decl.id().await;
+        customs
+            .receive_docs(Document::Declaration(DeclarationGeneric::Pending(decl)))
+            .await
+            .unwrap();
+        assert!(customs.declarations_ref().await.contains_key(&id));
+        let draft = Declaration::<Draft>::new().await;
+        assert!(customs
+            .receive_docs(Document::Declaration(DeclarationGeneric::Draft(draft)))
+            .await
+            .is_err());
+
+        let mut tax = Tax::new();
+        tax.set_declaration_id(id).await.set_price(100.0).await;
+        let mut billing = Billing::new().await;
+        billing.set_declaration_id(id).await.set_price(50.0).await;
+        let mut other = Billing::new().await;
+        other.set_declaration_id(Uuid::new_v4()).await;
+        customs
+            .receive_docs(Document::Tax(tax.clone()))
+            .await
+            .unwrap();
+        customs
+            .receive_docs(Document::Billing(billing.clone()))
+            .await
+            .unwrap();
+        assert!(customs
+            .receive_docs(Document::Billing(other))
+            .await
+            .is_err());
+
+        let docs = customs.documents_of(&id).await;
+        assert_eq!(docs.len(), 2);
+        assert!(docs.contains(&Document::Tax(tax)));
+        assert!(docs.contains(&Document::Billing(billing)));
+        assert!(customs.documents_of(&Uuid::new_v4()).await.is_empty());
+    }
+
+    async fn number_of(customs: &Customs, decl: &Declaration<Pending>) -> RegistrationNumber {
+        customs.declarations[decl.id_ref().await]
+            .registration_number()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn registration() {
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        let unregistered: Declaration<Pending> = Declaration::new().await.into();
+        customs.update_decl(unregistered.clone()).await.unwrap();
+        assert!(
+            customs.declarations_ref().await[unregistered.id_ref().await]
+                .registration_number_ref()
+                .await
+                .is_none()
+        );
+
+        customs
+            .set_registry(Some(Registry::new("10702020").await.unwrap()))
+            .await;
+        let first: Declaration<Pending> = Declaration::new().await.into();
+        let second: Declaration<Pending> = Declaration::new().await.into();
+        customs.update_decl(first.clone()).await.unwrap();
+        customs.update_decl(second.clone()).await.unwrap();
+        let first_number = number_of(&customs, &first).await;
+        assert_eq!(first_number.sequence().await, 1);
+        assert_eq!(number_of(&customs, &second).await.sequence().await, 2);
+
+        // Update keeps the number
+        let updated = customs.declarations[first.id_ref().await].clone();
+        customs.update_decl(updated).await.unwrap();
+        assert_eq!(number_of(&customs, &first).await, first_number);
+        assert_eq!(
+            customs.find_by_number(&first_number).await,
+            Some(first.id().await)
+        );
+
+        // Sequence survives save and load
+        let saved = serde_json::to_string(&customs).unwrap();
+        let mut loaded: Customs = serde_json::from_str(&saved).unwrap();
+        let third: Declaration<Pending> = Declaration::new().await.into();
+        loaded.update_decl(third.clone()).await.unwrap();
+        assert_eq!(number_of(&loaded, &third).await.sequence().await, 3);
+
+        // Relocated declaration is registered anew, customs without code drops the number
+        let mut other = Customs::new("Kazan", &Location::default()).await;
+        other
+            .set_registry(Some(Registry::new("10404000").await.unwrap()))
+            .await;
+        let relocated = customs
+            .remove_declaration(first.id_ref().await)
+            .await
+            .unwrap();
+        other.update_decl(relocated.clone()).await.unwrap();
+        let number = number_of(&other, &first).await;
+        assert_eq!(number.customs_code_ref().await, "10404000");
+        assert_eq!(other.find_by_number(&first_number).await, None);
+        assert_eq!(other.find_by_number(&number).await, Some(first.id().await));
+        let mut uncoded = Customs::new("Tver", &Location::default()).await;
+        uncoded.update_decl(relocated).await.unwrap();
+        assert_eq!(uncoded.find_by_number(&first_number).await, None);
+    }
+
+    #[tokio::test]
+    async fn signatures() {
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        let keys = KeyPair::generate().await;
+        let owner = Uuid::new_v4();
+        let mut draft = Declaration::new().await;
+        draft.sign(owner, &keys).await;
+        let signed: Declaration<Pending> = draft.clone().into();
+        let unsigned: Declaration<Pending> = Declaration::new().await.into();
+        customs.update_decl(unsigned.clone()).await.unwrap();
+        customs.update_decl(signed.clone()).await.unwrap();
+
+        let mut changed = signed.clone();
+        changed.set_destination("Elsewhere").await;
+        assert!(customs.update_decl(changed).await.is_err());
+
+        customs.set_require_signatures(true).await;
+        assert!(customs.update_decl(unsigned).await.is_err());
+        customs
+            .trusted_keys_mut()
+            .await
+            .insert(owner, KeyPair::generate().await.public_key().await);
+        assert!(customs.update_decl(signed.clone()).await.is_err());
+        customs
+            .trusted_keys_mut()
+            .await
+            .insert(owner, keys.public_key().await);
+        assert!(customs.update_decl(signed).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn forged_signature() {
+        use crate::errors::signature::Err as SErr;
+
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_require_signatures(true).await;
+        let owner = Uuid::new_v4();
+        customs
+            .trusted_keys_mut()
+            .await
+            .insert(owner, KeyPair::generate().await.public_key().await);
+        let forger = KeyPair::generate().await;
+        let stranger = Uuid::new_v4();
+
+        for signer in [owner, stranger] {
+            let mut draft = Declaration::new().await;
+            draft.sign(signer, &forger).await;
+            let forged: Declaration<Pending> = draft.into();
+            assert!(forged.verify_signature().await.is_ok());
+            let err = customs.update_decl(forged).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<SErr>(),
+                Some(SErr::UntrustedKey { .. })
+            ));
+        }
+        assert!(customs.declarations_ref().await.is_empty());
+    }
+}
@@ -1,8 +1,18 @@
 //! Declaration, main transferable object in the system
 
+use std::collections::BTreeMap;
+
 use chrono::Utc;
 use uuid::Uuid;
 
+use super::classifier::Classifier;
+use super::exchange::ExchangeRates;
+use super::misc::attachment::{Attachment, AttachmentKind};
+use super::misc::goods::GoodsItem;
+use super::misc::registration::RegistrationNumber;
+use super::misc::signature::{KeyPair, Signature};
+use super::validation::Validator;
+use crate::repository::blob_store::BlobStore;
 use crate::{prelude::*, utils::HasId};
 
 /// Declaration States
@@ -12,6 +22,9 @@ pub struct Draft;
 pub struct Pending;
 #[derive(Clone, Default, PartialEq, Eq, PartialOrd, Debug, Serialize, Deserialize)]
 pub struct Inspecting;
+/// Inspector asked declarant to amend some fields of declaration
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct InfoRequested;
 #[derive(Clone, Default, PartialEq, Eq, PartialOrd, Debug, Serialize, Deserialize)]
 pub struct Approved;
 #[derive(Clone, Default, PartialEq, Eq, PartialOrd, Debug, Serialize, Deserialize)]
@@ -20,11 +33,118 @@ pub trait IsState {}
 impl IsState for Draft {}
 impl IsState for Pending {}
 impl IsState for Inspecting {}
+impl IsState for InfoRequested {}
 impl IsState for Approved {}
 impl IsState for Rejected {}
 
+/// Urgency of declaration, more urgent declarations are handed to inspectors first
+#[derive(
+    Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize,
+)]
+pub enum Priority {
+    #[default]
+    Regular,
+    AirTransport,
+    Perishable,
+    Humanitarian,
+    LiveAnimals,
+}
+
+/// Inspector's final decision on declaration, i.e. why it was approved or rejected
+#[derive(Clone, Default, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Decision {
+    inspector_id: Uuid,
+    reason: String,
+    decided_at: chrono::DateTime<Utc>,
+}
+
+impl Decision {
+    pub async fn new(inspector_id: Uuid, reason: &str) -> Self {
+        Self {
+            inspector_id,
+            reason: reason.to_string(),
+            decided_at: Utc::now(),
+        }
+    }
+}
+
+/// Boilerplate
+impl Decision {
+    getter_ref!( { async } inspector_id: &Uuid, { async } reason: &str, { async } decided_at: &chrono::DateTime<Utc>);
+    getter!( { async } inspector_id: Uuid, { async } decided_at: chrono::DateTime<Utc>);
+}
+
+/// Inspector's request for additional information, lists fields declarant may amend
+#[derive(Clone, Default, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct InfoRequest {
+    inspector_id: Uuid,
+    /// Field names, goods line fields are named like `goods[0].code`
+    fields: Vec<String>,
+    message: String,
+    requested_at: chrono::DateTime<Utc>,
+}
+
+impl InfoRequest {
+    pub async fn new(inspector_id: Uuid, fields: &[&str], message: &str) -> Self {
+        Self {
+            inspector_id,
+            fields: fields.iter().map(ToString::to_string).collect(),
+            message: message.to_string(),
+            requested_at: Utc::now(),
+        }
+    }
+}
+
+/// Boilerplate
+impl InfoRequest {
+    getter_ref!( { async } inspector_id: &Uuid, { async } fields: &Vec<String>, { async } message: &str, { async } requested_at: &chrono::DateTime<Utc>);
+    getter!( { async } inspector_id: Uuid, { async } requested_at: chrono::DateTime<Utc>);
+}
+
+/// Change of a single field, values are kept as text
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// Field name, goods line fields are named like `goods[0].code`
+    field: String,
+    old: String,
+    new: String,
+}
+
+/// Boilerplate
+impl FieldChange {
+    getter_ref!( { async } field: &str, { async } old: &str, { async } new: &str);
+}
+
+/// Record of a change made to declaration. Revisions are never changed once made
+#[derive(Clone, Default, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Revision {
+    /// Revisions are numbered from 1 in order they were made
+    number: usize,
+    /// Participant or inspector, who made the change
+    author: Uuid,
+    created_at: chrono::DateTime<Utc>,
+    from_state: String,
+    to_state: String,
+    changes: Vec<FieldChange>,
+}
+
+/// Boilerplate
+impl Revision {
+    getter_ref!( { async } author: &Uuid, { async } created_at: &chrono::DateTime<Utc>, { async } from_state: &str, { async } to_state: &str, { async } changes: &Vec<FieldChange>);
+    getter!( { async } number: usize, { async } author: Uuid, { async } created_at: chrono::DateTime<Utc>);
+}
+
+/// Short name of the state type, e.g. "Pending"
+fn state_name<State>() -> &'static str {
+    let name = std::any::type_name::<State>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Any document participants and customs exchange. Billings and taxes
+/// refer to declaration they belong to by `declaration_id`
+#[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
 pub enum Document {
-    Declaration(Declaration),
+    Declaration(DeclarationGeneric),
     Billing(Billing),
     Tax(Tax),
 }
@@ -37,18 +157,48 @@ pub struct Declaration<State = Draft> {
     #[serde(with = "compact")]
     signed_by: Uuid,
     inspected_by: Option<Uuid>,
+    /// Single product of declarations made before goods lines,
+    /// see `goods` for the rest of declarations
     product_name: String,
     product_code: String,
     product_price: f64,
     product_quantity: i64,
     product_weight: f64,
     product_description: String,
+    #[serde(default)]
+    goods: Vec<GoodsItem>,
+    /// Currency of goods values (ISO 4217 code), empty for the national one
+    #[serde(default)]
+    currency: String,
     transport_type: String,
     transport_name: String,
+    /// Customs procedure code, e.g. "IM40" for release for domestic consumption
+    procedure: String,
     sender_name: String,
     receiver_name: String,
     destination: String,
     departure: String,
+    /// Region where declarant is registered as a tax payer
+    registration_region: String,
+    /// Assigned by customs, that accepted declaration
+    #[serde(default)]
+    registration_number: Option<RegistrationNumber>,
+    #[serde(default)]
+    priority: Priority,
+    /// Set, when declaration is approved or rejected
+    #[serde(default)]
+    decision: Option<Decision>,
+    /// Set, while inspector waits for declarant to amend declaration
+    #[serde(default)]
+    info_request: Option<InfoRequest>,
+    #[serde(default)]
+    revisions: Vec<Revision>,
+    /// Supporting documents, e.g. invoice or certificate of origin
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    /// Signature of `signed_by` over the content, see `canonical_bytes`
+    #[serde(default)]
+    signature: Option<Signature>,
     //state: std::marker::PhantomData<State>,  // This produces warnings from clippy (State doesnt
     // implement Sync (and Send for that matter). Might be unsafe to transfer between threads?
     // Or might be a false negative.
@@ -59,6 +209,13 @@ pub struct Declaration<State = Draft> {
     created_at: chrono::DateTime<Utc>,
     #[serde(skip)]
     updated_at: chrono::DateTime<Utc>,
+    /// When declaration was validated and sent, values are converted at the rate of this date
+    #[serde(default)]
+    submitted_at: Option<chrono::DateTime<Utc>>,
+    /// When declaration was taken by its current holder, i.e. customs for Pending
+    /// and inspector for Inspecting declarations
+    #[serde(skip)]
+    state_entered_at: chrono::DateTime<Utc>,
 }
 
 #[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
@@ -66,6 +223,7 @@ pub enum DeclarationGeneric {
     Draft(Declaration<Draft>),
     Pending(Declaration<Pending>),
     Inspecting(Declaration<Inspecting>),
+    InfoRequested(Declaration<InfoRequested>),
     Approved(Declaration<Approved>),
     Rejected(Declaration<Rejected>),
 }
@@ -101,6 +259,15 @@ impl GenericDowncast<'_, Inspecting> for DeclarationGeneric {
     }
 }
 
+impl GenericDowncast<'_, InfoRequested> for DeclarationGeneric {
+    fn downcast(&self) -> Option<&Declaration<InfoRequested>> {
+        match self {
+            Self::InfoRequested(decl) => Some(decl),
+            _ => None,
+        }
+    }
+}
+
 impl GenericDowncast<'_, Approved> for DeclarationGeneric {
     fn downcast(&self) -> Option<&Declaration<Approved>> {
         match self {
@@ -127,6 +294,7 @@ impl Default for DeclarationGeneric {
 
 mod logic {
     use super::*;
+    use crate::errors::signature::Err as SErr;
     use std::convert::From;
     use std::error::Error;
 
@@ -142,13 +310,335 @@ mod logic {
         }
     }
 
+    impl<State> Declaration<State> {
+        /// Goods lines of declaration. Declaration without them carries single
+        /// product in `product_*` fields, it is treated as the only line then
+        pub async fn items(&self) -> Vec<GoodsItem> {
+            if !self.goods.is_empty() {
+                return self.goods.clone();
+            }
+            let mut item = GoodsItem::new(
+                &self.product_code,
+                &self.product_description,
+                self.product_quantity as f64,
+                "",
+            )
+            .await;
+            item.set_gross_weight(self.product_weight)
+                .await
+                .set_net_weight(self.product_weight)
+                .await
+                .set_value(self.product_price)
+                .await;
+
+            vec![item]
+        }
+
+        /// Customs value of all goods lines
+        pub async fn total_value(&self) -> f64 {
+            let mut total = 0.0;
+            for item in self.items().await {
+                total += item.value().await;
+            }
+
+            total
+        }
+
+        /// Every field, that can be changed, by its name. Goods lines are named like `goods[0].code`
+        pub async fn named_fields(&self) -> BTreeMap<String, String> {
+            let mut fields: BTreeMap<String, String> = [
+                ("product_name", &self.product_name),
+                ("transport_type", &self.transport_type),
+                ("transport_name", &self.transport_name),
+                ("procedure", &self.procedure),
+                ("sender_name", &self.sender_name),
+                ("receiver_name", &self.receiver_name),
+                ("destination", &self.destination),
+                ("departure", &self.departure),
+                ("currency", &self.currency),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+            fields.insert("priority".to_string(), format!("{:?}", self.priority));
+            for (line, item) in self.items().await.iter().enumerate() {
+                let (strings, numbers) = item.fields().await;
+                let names = [
+                    "code",
+                    "description",
+                    "unit",
+                    "origin",
+                    "quantity",
+                    "gross_weight",
+                    "net_weight",
+                    "value",
+                ];
+                let values = strings
+                    .into_iter()
+                    .chain(numbers.iter().map(ToString::to_string));
+                for (name, value) in names.iter().zip(values) {
+                    fields.insert(format!("goods[{line}].{name}"), value);
+                }
+            }
+
+            fields
+        }
+
+        /// Fields, that differ in the other declaration. Fields missing on one side are empty
+        pub async fn diff<U>(&self, other: &Declaration<U>) -> Vec<FieldChange> {
+            let old = self.named_fields().await;
+            let new = other.named_fields().await;
+            let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+            names.sort_unstable();
+            names.dedup();
+
+            names
+                .into_iter()
+                .filter_map(|name| {
+                    let (old, new) = (old.get(name), new.get(name));
+                    (old != new).then(|| FieldChange {
+                        field: name.clone(),
+                        old: old.cloned().unwrap_or_default(),
+                        new: new.cloned().unwrap_or_default(),
+                    })
+                })
+                .collect()
+        }
+
+        /// Record changes made to the previous version of declaration. History is taken
+        /// from the previous version, so it can't be rewritten by the new one.
+        /// Nothing is recorded, if neither fields nor state have changed
+        pub async fn revise<U>(&mut self, author: Uuid, previous: &Declaration<U>) -> &mut Self {
+            self.revisions = previous.revisions.clone();
+            let changes = previous.diff(self).await;
+            let (from_state, to_state) = (state_name::<U>(), state_name::<State>());
+            if changes.is_empty() && from_state == to_state {
+                return self;
+            }
+            self.revisions.push(Revision {
+                number: self.revisions.len() + 1,
+                author,
+                created_at: Utc::now(),
+                from_state: from_state.to_string(),
+                to_state: to_state.to_string(),
+                changes,
+            });
+
+            self
+        }
+
+        /// History loaded from storage. Everything else records revisions through `revise`
+        pub(crate) async fn restore_revisions(&mut self, revisions: Vec<Revision>) -> &mut Self {
+            self.revisions = revisions;
+
+            self
+        }
+
+        /// Revisions, that changed the field
+        pub async fn revisions_of(&self, field: &str) -> Vec<&Revision> {
+            self.revisions
+                .iter()
+                .filter(|revision| revision.changes.iter().any(|change| change.field == field))
+                .collect()
+        }
+
+        pub async fn attachment(&self, id: &Uuid) -> Option<&Attachment> {
+            for attachment in &self.attachments {
+                if attachment.id_ref().await == id {
+                    return Some(attachment);
+                }
+            }
+
+            None
+        }
+
+        /// New draft with the same content, fresh id and timestamps. Everything,
+        /// that belongs to submission, i.e. number, decision, history, signature
+        /// and attached documents, is left behind
+        pub async fn to_draft(&self) -> Declaration<Draft> {
+            let now = Utc::now();
+            let id = Uuid::new_v4();
+            tracing::info!("Declaration {} created from {}", id, self.id);
+            Declaration {
+                id,
+                signed_by: self.signed_by,
+                product_name: self.product_name.clone(),
+                product_code: self.product_code.clone(),
+                product_price: self.product_price,
+                product_quantity: self.product_quantity,
+                product_weight: self.product_weight,
+                product_description: self.product_description.clone(),
+                goods: self.goods.clone(),
+                currency: self.currency.clone(),
+                transport_type: self.transport_type.clone(),
+                transport_name: self.transport_name.clone(),
+                procedure: self.procedure.clone(),
+                sender_name: self.sender_name.clone(),
+                receiver_name: self.receiver_name.clone(),
+                destination: self.destination.clone(),
+                departure: self.departure.clone(),
+                registration_region: self.registration_region.clone(),
+                priority: self.priority,
+                created_at: now,
+                updated_at: now,
+                ..Default::default()
+            }
+        }
+
+        /// Content, that is signed: id, owner, every named field and hashes of attachments.
+        /// Fields are sorted by name, so the same content always gives the same bytes
+        pub async fn canonical_bytes(&self) -> Vec<u8> {
+            let mut fields = self.named_fields().await;
+            fields.insert("id".to_string(), self.id.to_string());
+            fields.insert("signed_by".to_string(), self.signed_by.to_string());
+            // Content, that named fields don't cover, e.g. single product of declaration
+            // with goods lines
+            for (name, value) in [
+                ("registration_region", self.registration_region.clone()),
+                ("product_code", self.product_code.clone()),
+                ("product_description", self.product_description.clone()),
+                ("product_price", self.product_price.to_string()),
+                ("product_quantity", self.product_quantity.to_string()),
+                ("product_weight", self.product_weight.to_string()),
+            ] {
+                fields.insert(name.to_string(), value);
+            }
+            for (index, attachment) in self.attachments.iter().enumerate() {
+                fields.insert(
+                    format!("attachments[{index}].hash"),
+                    attachment.hash_ref().await.to_string(),
+                );
+            }
+
+            serde_json::to_vec(&fields).unwrap_or_default()
+        }
+
+        /// Sign declaration on behalf of signer, who becomes its owner.
+        /// Any change made afterwards invalidates the signature
+        pub async fn sign(&mut self, signer: Uuid, keys: &KeyPair) -> &mut Self {
+            self.signed_by = signer;
+            self.signature = Some(keys.sign(signer, &self.canonical_bytes().await).await);
+
+            self
+        }
+
+        /// Checks, that declaration is signed by its owner and wasn't changed since
+        pub async fn verify_signature(&self) -> Result<(), SErr> {
+            let signature = self.signature.as_ref().ok_or(SErr::NotSigned(self.id))?;
+            let signer = signature.signer().await;
+            if signer != self.signed_by {
+                return Err(SErr::SignerMismatch {
+                    id: self.id,
+                    signer,
+                });
+            }
+            if !signature.verify(&self.canonical_bytes().await).await {
+                return Err(SErr::InvalidSignature(self.id));
+            }
+
+            Ok(())
+        }
+
+        /// Total value in national currency at the rate of submission date.
+        /// Declarations, that are not submitted yet, are valued at the current rate
+        pub async fn customs_value(&self, rates: &ExchangeRates) -> Result<f64, Box<dyn Error>> {
+            let date = self.submitted_at.unwrap_or_else(Utc::now).date_naive();
+
+            Ok(rates
+                .convert(self.total_value().await, &self.currency, date)
+                .await?)
+        }
+    }
+
+    impl DeclarationGeneric {
+        pub async fn id(&self) -> Uuid {
+            match self {
+                Self::Draft(decl) => decl.id().await,
+                Self::Pending(decl) => decl.id().await,
+                Self::Inspecting(decl) => decl.id().await,
+                Self::InfoRequested(decl) => decl.id().await,
+                Self::Approved(decl) => decl.id().await,
+                Self::Rejected(decl) => decl.id().await,
+            }
+        }
+
+        /// See `Declaration::to_draft`
+        pub async fn to_draft(&self) -> Declaration<Draft> {
+            match self {
+                Self::Draft(decl) => decl.to_draft().await,
+                Self::Pending(decl) => decl.to_draft().await,
+                Self::Inspecting(decl) => decl.to_draft().await,
+                Self::InfoRequested(decl) => decl.to_draft().await,
+                Self::Approved(decl) => decl.to_draft().await,
+                Self::Rejected(decl) => decl.to_draft().await,
+            }
+        }
+    }
+
     impl Declaration<Draft> {
+        /// Clear fields by their names, see `named_fields`. Numbers are set to zero
+        pub async fn blank(&mut self, fields: &[&str]) -> Result<&mut Self, Box<dyn Error>> {
+            const NUMERIC: [&str; 7] = [
+                "product_price",
+                "product_weight",
+                "product_quantity",
+                "quantity",
+                "gross_weight",
+                "net_weight",
+                "value",
+            ];
+            for field in fields {
+                let name = field.rsplit('.').next().unwrap_or(field);
+                let value = if NUMERIC.contains(&name) { "0" } else { "" };
+                self.set_field(field, value).await?;
+            }
+
+            Ok(self)
+        }
+
+        /// Save document content to blob store and attach it to declaration
+        #[tracing::instrument(skip(store, content))]
+        pub async fn attach(
+            &mut self,
+            store: &BlobStore,
+            kind: AttachmentKind,
+            file_name: &str,
+            media_type: &str,
+            content: &[u8],
+        ) -> Result<Attachment, Box<dyn Error>> {
+            let hash = store.put(content).await?;
+            let attachment = Attachment::new(
+                kind,
+                file_name,
+                media_type,
+                content.len() as u64,
+                &hash,
+                self.signed_by,
+            )
+            .await;
+            tracing::info!("{} attached to declaration {}", file_name, self.id);
+            self.attachments.push(attachment.clone());
+
+            Ok(attachment)
+        }
+
+        /// Remove attachment from declaration. Content stays in blob store,
+        /// other declarations may have the same document attached
+        pub async fn detach(&mut self, id: &Uuid) -> Option<Attachment> {
+            let mut position = None;
+            for (index, attachment) in self.attachments.iter().enumerate() {
+                if attachment.id_ref().await == id {
+                    position = Some(index);
+                    break;
+                }
+            }
+
+            Some(self.attachments.remove(position?))
+        }
+
         pub async fn is_filled(&self) -> bool {
             for value in [
                 &self.departure,
-                &self.product_name,
-                &self.product_code,
-                &self.product_description,
                 &self.transport_type,
                 &self.transport_name,
                 &self.sender_name,
@@ -161,14 +651,8 @@ mod logic {
                 }
             }
 
-            for value in [self.product_price, self.product_weight] {
-                if value == f64::default() {
-                    return false;
-                }
-            }
-
-            for value in [self.product_quantity] {
-                if value == i64::default() {
+            for item in self.items().await {
+                if !item.is_filled().await {
                     return false;
                 }
             }
@@ -176,12 +660,55 @@ mod logic {
             true
         }
 
+        /// Checks declaration against the default rules, see `Validator::default`.
+        /// Commodity codes are checked too, once classifier is loaded, see `Classifier::init`
         pub async fn validate(&self) -> Result<Declaration<Pending>, Box<dyn Error>> {
-            if !self.is_filled().await {
-                return Err(Box::new(DErr::DeclarationNotComplete(self.id)));
+            let validator = match Classifier::shared().await {
+                Some(classifier) => Validator::classified(classifier).await,
+                None => Validator::default(),
+            };
+            self.validate_by(&validator).await
+        }
+
+        /// Declaration ready to be sent, or every field, that breaks the rules
+        pub async fn validate_by(
+            &self,
+            validator: &Validator,
+        ) -> Result<Declaration<Pending>, Box<dyn Error>> {
+            let violations = validator.check(self).await;
+            if !violations.is_empty() {
+                return Err(Box::new(DErr::Invalid(self.id, violations)));
+            }
+            if self.signature.is_some() {
+                self.verify_signature().await?;
+            }
+
+            let mut submitted = Declaration::<Pending>::from(self.clone());
+            submitted.submitted_at = Some(Utc::now());
+
+            Ok(submitted)
+        }
+    }
+
+    impl Declaration<InfoRequested> {
+        /// Change field, that inspector flagged. Numeric fields are parsed from value
+        pub async fn amend(
+            &mut self,
+            field: &str,
+            value: &str,
+        ) -> Result<&mut Self, Box<dyn Error>> {
+            let flagged = self.info_request.as_ref().map_or(false, |request| {
+                request.fields.iter().any(|name| name == field)
+            });
+            if !flagged {
+                return Err(Box::new(DErr::FieldNotFlagged {
+                    id: self.id,
+                    field: field.to_string(),
+                }));
             }
+            self.set_field(field, value).await?;
 
-            Ok(Declaration::<Pending>::from(self.clone()))
+            Ok(self)
         }
     }
 
@@ -196,15 +723,28 @@ mod logic {
             product_quantity: value.product_quantity,
             product_weight: value.product_weight,
             product_description: value.product_description,
+            goods: value.goods,
+            currency: value.currency,
             transport_type: value.transport_type,
             transport_name: value.transport_name,
+            procedure: value.procedure,
             sender_name: value.sender_name,
             receiver_name: value.receiver_name,
             destination: value.destination,
             departure: value.departure,
+            registration_region: value.registration_region,
+            registration_number: value.registration_number,
+            priority: value.priority,
+            decision: value.decision,
+            info_request: value.info_request,
+            revisions: value.revisions,
+            attachments: value.attachments,
+            signature: value.signature,
             state: std::marker::PhantomData,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            submitted_at: value.submitted_at,
+            state_entered_at: value.state_entered_at,
         }
     }
 
@@ -226,6 +766,18 @@ mod logic {
         }
     }
 
+    impl From<Declaration<Inspecting>> for Declaration<InfoRequested> {
+        fn from(value: Declaration<Inspecting>) -> Self {
+            copy(value)
+        }
+    }
+
+    impl From<Declaration<InfoRequested>> for Declaration<Inspecting> {
+        fn from(value: Declaration<InfoRequested>) -> Self {
+            copy(value)
+        }
+    }
+
     impl From<Declaration<Inspecting>> for Declaration<Approved> {
         fn from(value: Declaration<Inspecting>) -> Self {
             copy(value)
@@ -246,26 +798,92 @@ mod logic {
             }
         }
     }
+
+    impl Document {
+        /// Id of declaration the document is, or belongs to
+        pub async fn declaration_id(&self) -> Uuid {
+            match self {
+                Self::Declaration(decl) => decl.id().await,
+                Self::Billing(billing) => *billing.declaration_id_ref().await,
+                Self::Tax(tax) => *tax.declaration_id_ref().await,
+            }
+        }
+    }
+}
+
+/// Private methods
+impl<State> Declaration<State> {
+    /// Set field by its name, see `named_fields`. Numeric fields are parsed from value
+    async fn set_field(&mut self, field: &str, value: &str) -> Result<(), DErr> {
+        let id = self.id;
+        let invalid = || DErr::InvalidField {
+            id,
+            field: field.to_string(),
+            value: value.to_string(),
+        };
+        if let Some(rest) = field.strip_prefix("goods[") {
+            let (line, item_field) = rest.split_once("].").ok_or_else(invalid)?;
+            let line: usize = line.parse().map_err(|_| invalid())?;
+            // Single product turns into goods line once it is amended
+            if self.goods.is_empty() {
+                self.goods = self.items().await;
+            }
+            let item = self.goods.get_mut(line).ok_or_else(invalid)?;
+            if !item.set_field(item_field, value).await {
+                return Err(invalid());
+            }
+        } else {
+            match field {
+                "product_name" => self.product_name = value.to_string(),
+                "product_code" => self.product_code = value.to_string(),
+                "product_description" => self.product_description = value.to_string(),
+                "transport_type" => self.transport_type = value.to_string(),
+                "transport_name" => self.transport_name = value.to_string(),
+                "procedure" => self.procedure = value.to_string(),
+                "sender_name" => self.sender_name = value.to_string(),
+                "receiver_name" => self.receiver_name = value.to_string(),
+                "destination" => self.destination = value.to_string(),
+                "departure" => self.departure = value.to_string(),
+                "currency" => self.currency = value.to_string(),
+                "product_price" => self.product_price = value.parse().map_err(|_| invalid())?,
+                "product_weight" => {
+                    self.product_weight = value.parse().map_err(|_| invalid())?;
+                }
+                "product_quantity" => {
+                    self.product_quantity = value.parse().map_err(|_| invalid())?;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        self.updated_at = Utc::now();
+
+        Ok(())
+    }
 }
 
 /// Boilerplate
 impl<State> Declaration<State> {
-    pub async fn fields(&self) -> ([String; 9], [f64; 2], [i64; 1]) {
-        (
-            [
-                self.product_name.clone(),
-                self.product_code.clone(),
-                self.product_description.clone(),
-                self.transport_type.clone(),
-                self.transport_name.clone(),
-                self.sender_name.clone(),
-                self.receiver_name.clone(),
-                self.destination.clone(),
-                self.departure.clone(),
-            ],
-            [self.product_price, self.product_weight],
-            [self.product_quantity],
-        )
+    /// Textual and numeric fields, fields of goods lines follow the declaration's own ones
+    pub async fn fields(&self) -> (Vec<String>, Vec<f64>) {
+        let mut strings = vec![
+            self.product_name.clone(),
+            self.transport_type.clone(),
+            self.transport_name.clone(),
+            self.procedure.clone(),
+            self.sender_name.clone(),
+            self.receiver_name.clone(),
+            self.destination.clone(),
+            self.departure.clone(),
+        ];
+        let mut numbers = Vec::new();
+        for item in self.items().await {
+            let (item_strings, item_numbers) = item.fields().await;
+            strings.extend(item_strings);
+            numbers.extend(item_numbers);
+        }
+
+        (strings, numbers)
     }
     getter_ref!(
         { async } id: &Uuid,
@@ -277,14 +895,26 @@ impl<State> Declaration<State> {
         { async } product_quantity: &i64,
         { async } product_weight: &f64,
         { async } product_description: &str,
+        { async } goods: &Vec<GoodsItem>,
+        { async } currency: &str,
         { async } transport_type: &str,
         { async } transport_name: &str,
+        { async } procedure: &str,
         { async } sender_name: &str,
         { async } receiver_name: &str,
         { async } destination: &str,
         { async } departure: &str,
+        { async } registration_region: &str,
+        { async } registration_number: &Option<RegistrationNumber>,
+        { async } priority: &Priority,
+        { async } decision: &Option<Decision>,
+        { async } info_request: &Option<InfoRequest>,
+        { async } revisions: &Vec<Revision>,
+        { async } attachments: &Vec<Attachment>,
+        { async } signature: &Option<Signature>,
         { async } created_at: &chrono::DateTime<Utc>,
-        { async } updated_at: &chrono::DateTime<Utc>
+        { async } updated_at: &chrono::DateTime<Utc>,
+        { async } state_entered_at: &chrono::DateTime<Utc>
     );
     getter_mut!(
         { async } id: &mut Uuid,
@@ -295,14 +925,20 @@ impl<State> Declaration<State> {
         { async } product_quantity: &mut i64,
         { async } product_weight: &mut f64,
         { async } product_description: &mut String,
+        { async } goods: &mut Vec<GoodsItem>,
+        { async } currency: &mut String,
         { async } transport_type: &mut String,
         { async } transport_name: &mut String,
+        { async } procedure: &mut String,
         { async } sender_name: &mut String,
         { async } receiver_name: &mut String,
         { async } destination: &mut String,
         { async } departure: &mut String,
+        { async } registration_region: &mut String,
+        { async } priority: &mut Priority,
         { async } created_at: &mut chrono::DateTime<Utc>,
-        { async } updated_at: &mut chrono::DateTime<Utc>
+        { async } updated_at: &mut chrono::DateTime<Utc>,
+        { async } state_entered_at: &mut chrono::DateTime<Utc>
     );
 
     setter!(
@@ -315,14 +951,26 @@ impl<State> Declaration<State> {
         { async } product_quantity: i64,
         { async } product_weight: f64,
         { async } product_description: &str,
+        { async } goods: Vec<GoodsItem>,
+        { async } currency: &str,
         { async } transport_type: &str,
         { async } transport_name: &str,
+        { async } procedure: &str,
         { async } sender_name: &str,
         { async } receiver_name: &str,
         { async } destination: &str,
         { async } departure: &str,
+        { async } registration_region: &str,
+        { async } registration_number: Option<RegistrationNumber>,
+        { async } priority: Priority,
+        { async } decision: Option<Decision>,
+        { async } info_request: Option<InfoRequest>,
+        { async } attachments: Vec<Attachment>,
+        { async } signature: Option<Signature>,
         { async } created_at: chrono::DateTime<Utc>,
-        { async } updated_at: chrono::DateTime<Utc>
+        { async } updated_at: chrono::DateTime<Utc>,
+        { async } submitted_at: Option<chrono::DateTime<Utc>>,
+        { async } state_entered_at: chrono::DateTime<Utc>
     );
 
     getter!(
@@ -332,8 +980,12 @@ impl<State> Declaration<State> {
         { async } product_price: f64,
         { async } product_quantity: i64,
         { async } product_weight: f64,
+        { async } registration_number: Option<RegistrationNumber>,
+        { async } priority: Priority,
         { async } created_at: chrono::DateTime<Utc>,
-        { async } updated_at: chrono::DateTime<Utc>
+        { async } updated_at: chrono::DateTime<Utc>,
+        { async } submitted_at: Option<chrono::DateTime<Utc>>,
+        { async } state_entered_at: chrono::DateTime<Utc>
     );
 }
 
@@ -488,7 +1140,7 @@ mod tests {
         d.set_id("f6d4f3c4-2b1c-4c27-8e75-7f8b9c9b9a9e".parse().unwrap())
             .await;
         d.set_product_name("product name").await;
-        d.set_product_code("product code").await;
+        d.set_product_code("0101210000").await;
         d.set_product_price(1.0).await;
         d.set_product_quantity(2).await;
         d.set_product_weight(3.0).await;
@@ -507,7 +1159,7 @@ mod tests {
             .set_id("f6d4f3c4-2b1c-4c27-8e75-7f8b9c9b9a9e".parse().unwrap())
             .await;
         d_pending.set_product_name("product name").await;
-        d_pending.set_product_code("product code").await;
+        d_pending.set_product_code("0101210000").await;
         d_pending.set_product_price(1.0).await;
         d_pending.set_product_quantity(2).await;
         d_pending.set_product_weight(3.0).await;
@@ -525,7 +1177,219 @@ mod tests {
 
         let d_validated = d.validate().await;
         assert!(d_validated.is_ok());
-        assert_eq!(d_pending, d_validated.unwrap());
+        let d_validated = d_validated.unwrap();
+        assert!(d_validated.submitted_at().await.is_some());
+        d_pending
+            .set_submitted_at(d_validated.submitted_at().await)
+            .await;
+        assert_eq!(d_pending, d_validated);
+    }
+
+    #[tokio::test]
+    async fn revisions() {
+        let author = Uuid::new_v4();
+        let mut draft = super::Declaration::<Draft>::new().await;
+        draft.set_destination("Moscow").await;
+        let mut edited = draft.clone();
+        edited
+            .set_destination("Kazan")
+            .await
+            .set_product_price(10.0)
+            .await;
+
+        let changes = draft.diff(&edited).await;
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].field_ref().await, "destination");
+        assert_eq!(changes[0].old_ref().await, "Moscow");
+        assert_eq!(changes[0].new_ref().await, "Kazan");
+        assert_eq!(changes[1].field_ref().await, "goods[0].value");
+
+        edited.revise(author, &draft).await;
+        // Same state and no changes, nothing to record
+        let unchanged = edited.clone();
+        edited.revise(author, &unchanged).await;
+        let mut pending: super::Declaration<Pending> = edited.clone().into();
+        pending.revise(author, &edited).await;
+
+        let revisions = pending.revisions_ref().await;
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[1].number().await, 2);
+        assert_eq!(revisions[1].from_state_ref().await, "Draft");
+        assert_eq!(revisions[1].to_state_ref().await, "Pending");
+        assert!(revisions[1].changes_ref().await.is_empty());
+        assert_eq!(pending.revisions_of("destination").await.len(), 1);
+        assert!(pending.revisions_of("departure").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn amend() {
+        let mut d = super::Declaration::<InfoRequested>::default();
+        d.set_product_code("0101").await;
+        d.set_info_request(Some(
+            InfoRequest::new(
+                Uuid::new_v4(),
+                &["product_price", "goods[0].net_weight", "destination"],
+                "Check it",
+            )
+            .await,
+        ))
+        .await;
+
+        d.amend("destination", "Kazan")
+            .await
+            .unwrap()
+            .amend("product_price", "12.5")
+            .await
+            .unwrap();
+        assert_eq!(d.destination_ref().await, "Kazan");
+        assert!((d.product_price().await - 12.5).abs() < f64::EPSILON);
+        assert!(d.amend("product_price", "twelve").await.is_err());
+        assert!(d.amend("departure", "Moscow").await.is_err());
+
+        // Single product becomes goods line
+        d.amend("goods[0].net_weight", "3").await.unwrap();
+        assert_eq!(d.goods_ref().await.len(), 1);
+        assert_eq!(d.goods_ref().await[0].code_ref().await, "0101");
+        assert!((d.goods_ref().await[0].net_weight().await - 3.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn signature() {
+        use crate::errors::signature::Err as SErr;
+
+        let keys = KeyPair::generate().await;
+        let owner = Uuid::new_v4();
+        let mut d = super::Declaration::<Draft>::new().await;
+        d.set_transport_type("transport type").await;
+        d.set_transport_name("transport name").await;
+        d.set_sender_name("sender name").await;
+        d.set_receiver_name("receiver name").await;
+        d.set_destination("destination").await;
+        d.set_departure("departure").await;
+        d.set_product_code("0101210000").await;
+        d.set_product_name("Horse").await;
+        d.set_product_description("Pure-bred breeding horse").await;
+        d.set_product_price(5000.0).await;
+        d.set_product_quantity(1).await;
+        d.set_product_weight(500.0).await;
+        assert!(matches!(
+            d.verify_signature().await,
+            Err(SErr::NotSigned(_))
+        ));
+
+        d.sign(owner, &keys).await;
+        assert_eq!(d.signed_by().await, owner);
+        assert!(d.verify_signature().await.is_ok());
+        let pending = d.validate().await.unwrap();
+        assert!(pending.verify_signature().await.is_ok());
+
+        // Any change after signing invalidates the signature
+        d.set_departure("elsewhere").await;
+        assert!(matches!(
+            d.verify_signature().await,
+            Err(SErr::InvalidSignature(_))
+        ));
+        assert!(d.validate().await.is_err());
+        d.sign(owner, &keys).await;
+        assert!(d.validate().await.is_ok());
+
+        // Region routes dispatch, so it is signed as well
+        d.set_registration_region("Moscow").await;
+        assert!(matches!(
+            d.verify_signature().await,
+            Err(SErr::InvalidSignature(_))
+        ));
+        d.sign(owner, &keys).await;
+        assert!(d.verify_signature().await.is_ok());
+
+        d.set_signed_by(Uuid::new_v4()).await;
+        assert!(matches!(
+            d.verify_signature().await,
+            Err(SErr::SignerMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn goods_items() {
+        let mut d = super::Declaration::<Draft>::new().await;
+        d.set_transport_type("transport type").await;
+        d.set_transport_name("transport name").await;
+        d.set_sender_name("sender name").await;
+        d.set_receiver_name("receiver name").await;
+        d.set_destination("destination").await;
+        d.set_departure("departure").await;
+        assert!(!d.is_filled().await);
+
+        let mut horse = GoodsItem::new("0101210000", "Horse", 2.0, "pcs").await;
+        horse
+            .set_gross_weight(1000.0)
+            .await
+            .set_net_weight(900.0)
+            .await
+            .set_value(5000.0)
+            .await;
+        let mut cheese = GoodsItem::new("0406", "Cheese", 10.0, "kg").await;
+        cheese
+            .set_gross_weight(11.0)
+            .await
+            .set_net_weight(10.0)
+            .await
+            .set_value(300.0)
+            .await;
+        d.set_goods(vec![horse, cheese.clone()]).await;
+        assert!(d.is_filled().await);
+        assert_eq!(d.items().await.len(), 2);
+        assert!((d.total_value().await - 5300.0).abs() < f64::EPSILON);
+        let (strings, numbers) = d.fields().await;
+        assert_eq!(strings.len(), 8 + 2 * 4);
+        assert_eq!(numbers.len(), 2 * 4);
+
+        // Bundled classifier knows horses, but 0406 is only a heading
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tnved.json");
+        let bundled = Validator::classified(Arc::new(Classifier::load(path).await.unwrap())).await;
+        let err = d.validate_by(&bundled).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Declaration has invalid fields: goods[1].code. UUID = {}",
+                d.id().await
+            )
+        );
+        d.goods_mut().await[1].set_code("0406105000").await;
+        assert!(d.validate_by(&bundled).await.is_ok());
+
+        // Every code, that classifier doesn't know, is reported at once
+        let classifier = Classifier::from_json(r#"{"04": "Dairy", "0406": "Cheese"}"#)
+            .await
+            .unwrap();
+        let validator = Validator::new(vec![crate::models::validation::Rule::Classified {
+            field: "goods[*].code".to_string(),
+            classifier: Arc::new(classifier),
+        }])
+        .await;
+        let err = d.validate_by(&validator).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Declaration has invalid fields: goods[0].code, goods[1].code. UUID = {}",
+                d.id().await
+            )
+        );
+
+        let mut rates = ExchangeRates::new("RUB").await;
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+        rates.set_rate("USD", date, 80.0).await;
+        d.set_currency("USD").await;
+        let submitted = d.validate().await.unwrap();
+        let expected = 5300.0 * 80.0;
+        assert!((submitted.customs_value(&rates).await.unwrap() - expected).abs() < 1e-6);
+        d.set_currency("GBP").await;
+        assert!(d.customs_value(&rates).await.is_err());
+
+        d.goods_mut().await[1].set_net_weight(12.0).await;
+        assert!(d.validate().await.is_err());
+        d.goods_mut().await[1].set_description("").await;
+        assert!(!d.is_filled().await);
     }
 }
 
@@ -0,0 +1,150 @@
+//! Exchange rates, used to get customs value in national currency
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use tokio::sync::OnceCell;
+
+use crate::errors::exchange::Err as ExErr;
+use crate::prelude::*;
+
+/// Rates bundled with the app, see `CustomsParams::new`
+const BUNDLED: &str = include_str!("../../resources/rates.json");
+/// Bundled rates, parsed on first use
+static PARSED: OnceCell<ExchangeRates> = OnceCell::const_new();
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ExchangeRates {
+    /// Currency customs values are converted to, e.g. "RUB"
+    national: String,
+    /// Units of national currency per unit of currency.
+    /// Rate is in force from its date until the next one
+    rates: HashMap<String, BTreeMap<NaiveDate, f64>>,
+}
+
+impl ExchangeRates {
+    pub async fn new(national: &str) -> Self {
+        Self {
+            national: national.to_uppercase(),
+            ..Default::default()
+        }
+    }
+
+    /// Rates from JSON object with national currency and dated rates of other currencies
+    pub async fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rates: Self = serde_json::from_str(json)?;
+        rates.national = rates.national.to_uppercase();
+        rates.rates = rates
+            .rates
+            .into_iter()
+            .map(|(currency, rates)| (currency.to_uppercase(), rates))
+            .collect();
+
+        Ok(rates)
+    }
+
+    /// Rates from bundled `resources/rates.json`, parsed once
+    pub async fn bundled() -> Result<Self, Box<dyn Error>> {
+        PARSED
+            .get_or_try_init(|| Self::from_json(BUNDLED))
+            .await
+            .cloned()
+    }
+
+    /// Load rates from local data file, e.g. bundled `resources/rates.json`
+    #[tracing::instrument(skip(path))]
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        tracing::info!("Loading exchange rates from {}", path.display());
+
+        Self::from_json(&tokio::fs::read_to_string(path).await?).await
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use super::*;
+
+    impl super::ExchangeRates {
+        /// Set rate of currency in force from the date
+        pub async fn set_rate(&mut self, currency: &str, date: NaiveDate, rate: f64) -> &mut Self {
+            self.rates
+                .entry(currency.to_uppercase())
+                .or_default()
+                .insert(date, rate);
+
+            self
+        }
+
+        /// Rate in force at the date. Empty currency stands for the national one
+        pub async fn rate(&self, currency: &str, date: NaiveDate) -> Result<f64, ExErr> {
+            let currency = currency.to_uppercase();
+            if currency.is_empty() || currency == self.national {
+                return Ok(1.0);
+            }
+            let rates = self
+                .rates
+                .get(&currency)
+                .ok_or_else(|| ExErr::UnknownCurrency(currency.clone()))?;
+
+            rates
+                .range(..=date)
+                .next_back()
+                .map(|(_, rate)| *rate)
+                .ok_or(ExErr::RateNotFound(currency, date))
+        }
+
+        /// Convert amount to national currency at the rate in force at the date
+        pub async fn convert(
+            &self,
+            amount: f64,
+            currency: &str,
+            date: NaiveDate,
+        ) -> Result<f64, ExErr> {
+            Ok(amount * self.rate(currency, date).await?)
+        }
+    }
+}
+
+/// Boilerplate
+impl ExchangeRates {
+    getter_ref!( { async } national: &str, { async } rates: &HashMap<String, BTreeMap<NaiveDate, f64>>);
+    setter!( { async } national: &str);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::ExchangeRates;
+    use crate::errors::exchange::Err as ExErr;
+
+    #[tokio::test]
+    async fn convert() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/rates.json");
+        let rates = ExchangeRates::load(path).await.unwrap();
+        assert_eq!(rates, ExchangeRates::bundled().await.unwrap());
+        let date = |month, day| NaiveDate::from_ymd_opt(2023, month, day).unwrap();
+
+        assert!((rates.convert(10.0, "rub", date(5, 15)).await.unwrap() - 10.0).abs() < 1e-9);
+        assert!((rates.convert(10.0, "", date(5, 15)).await.unwrap() - 10.0).abs() < 1e-9);
+        // Rate of 1st of May is still in force
+        assert!((rates.convert(10.0, "usd", date(5, 31)).await.unwrap() - 805.2).abs() < 1e-9);
+        assert!((rates.convert(10.0, "USD", date(6, 1)).await.unwrap() - 809.5).abs() < 1e-9);
+        assert!(matches!(
+            rates.convert(10.0, "USD", date(3, 31)).await,
+            Err(ExErr::RateNotFound(..))
+        ));
+        assert!(matches!(
+            rates.convert(10.0, "GBP", date(5, 15)).await,
+            Err(ExErr::UnknownCurrency(_))
+        ));
+    }
+}
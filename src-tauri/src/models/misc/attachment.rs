@@ -0,0 +1,61 @@
+//! Supporting documents of declaration. Only metadata is kept with declaration,
+//! content is kept in blob store under its hash
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::prelude::*;
+
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub enum AttachmentKind {
+    Invoice,
+    Contract,
+    CertificateOfOrigin,
+    /// Permits and licences, e.g. veterinary certificate
+    Permit,
+    #[default]
+    Other,
+}
+
+#[derive(Clone, Default, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    id: Uuid,
+    kind: AttachmentKind,
+    file_name: String,
+    /// MIME type, e.g. "application/pdf"
+    media_type: String,
+    /// Size of content in bytes
+    size: u64,
+    /// Hex encoded SHA-256 of content, content is found by it in blob store
+    hash: String,
+    uploaded_by: Uuid,
+    uploaded_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    pub async fn new(
+        kind: AttachmentKind,
+        file_name: &str,
+        media_type: &str,
+        size: u64,
+        hash: &str,
+        uploaded_by: Uuid,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            file_name: file_name.to_string(),
+            media_type: media_type.to_string(),
+            size,
+            hash: hash.to_string(),
+            uploaded_by,
+            uploaded_at: Utc::now(),
+        }
+    }
+}
+
+/// Boilerplate
+impl Attachment {
+    getter_ref!( { async } id: &Uuid, { async } kind: &AttachmentKind, { async } file_name: &str, { async } media_type: &str, { async } hash: &str);
+    getter!( { async } id: Uuid, { async } size: u64, { async } uploaded_by: Uuid, { async } uploaded_at: DateTime<Utc>);
+}
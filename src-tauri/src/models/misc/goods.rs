@@ -0,0 +1,115 @@
+//! Goods item, i.e. single line of declaration
+
+use crate::prelude::*;
+
+#[derive(Clone, Default, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct GoodsItem {
+    /// Commodity code, e.g. "0101210000" for pure-bred breeding horses
+    code: String,
+    description: String,
+    quantity: f64,
+    /// Unit of quantity, e.g. "pcs", "kg" or "l"
+    unit: String,
+    /// Weight in kilograms, with packaging
+    gross_weight: f64,
+    /// Weight in kilograms, without packaging
+    net_weight: f64,
+    /// Country of origin
+    origin: String,
+    /// Customs value of the whole line
+    value: f64,
+}
+
+impl GoodsItem {
+    pub async fn new(code: &str, description: &str, quantity: f64, unit: &str) -> Self {
+        Self {
+            code: code.to_string(),
+            description: description.to_string(),
+            quantity,
+            unit: unit.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    impl super::GoodsItem {
+        /// Unit and origin may be unknown, e.g. for declarations made before goods lines
+        pub async fn is_filled(&self) -> bool {
+            !self.code.is_empty()
+                && !self.description.is_empty()
+                && self.quantity > f64::default()
+                && self.gross_weight > f64::default()
+                && self.value > f64::default()
+        }
+
+        /// Set field by its name, false if there is no such field or value can't be parsed
+        pub async fn set_field(&mut self, field: &str, value: &str) -> bool {
+            let number = value.parse::<f64>();
+            match (field, number) {
+                ("code", _) => self.code = value.to_string(),
+                ("description", _) => self.description = value.to_string(),
+                ("unit", _) => self.unit = value.to_string(),
+                ("origin", _) => self.origin = value.to_string(),
+                ("quantity", Ok(number)) => self.quantity = number,
+                ("gross_weight", Ok(number)) => self.gross_weight = number,
+                ("net_weight", Ok(number)) => self.net_weight = number,
+                ("value", Ok(number)) => self.value = number,
+                _ => return false,
+            }
+
+            true
+        }
+
+        pub async fn fields(&self) -> ([String; 4], [f64; 4]) {
+            (
+                [
+                    self.code.clone(),
+                    self.description.clone(),
+                    self.unit.clone(),
+                    self.origin.clone(),
+                ],
+                [
+                    self.quantity,
+                    self.gross_weight,
+                    self.net_weight,
+                    self.value,
+                ],
+            )
+        }
+    }
+}
+
+/// Boilerplate
+impl GoodsItem {
+    getter_ref!( { async } code: &str, { async } description: &str, { async } unit: &str, { async } origin: &str);
+    getter_mut!( { async } code: &mut String, { async } description: &mut String, { async } unit: &mut String, { async } origin: &mut String);
+    setter!( { async } code: &str, { async } description: &str, { async } quantity: f64, { async } unit: &str, { async } gross_weight: f64, { async } net_weight: f64, { async } origin: &str, { async } value: f64);
+    getter!( { async } quantity: f64, { async } gross_weight: f64, { async } net_weight: f64, { async } value: f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GoodsItem;
+
+    #[tokio::test]
+    async fn set_field() {
+        let mut item = GoodsItem::new("0101210000", "Horse", 2.0, "pcs").await;
+        item.set_gross_weight(1000.0).await.set_value(5000.0).await;
+        assert!(item.is_filled().await);
+
+        assert!(item.set_field("quantity", "-1").await);
+        assert!(!item.is_filled().await);
+        assert!(item.set_field("origin", "DE").await);
+        assert_eq!(item.origin_ref().await, "DE");
+        assert!(!item.set_field("net_weight", "heavy").await);
+        assert!(!item.set_field("colour", "brown").await);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{prelude::*, utils::HasId};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Offset, Utc};
 use uuid::Uuid;
 #[derive(Clone, Default, Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
 pub struct Location {
@@ -8,28 +8,25 @@ pub struct Location {
     country: String,
     region: String,
     city: String,
-    #[serde(with = "ts_seconds")]
-    timezone: DateTime<Utc>,
+    /// Offset from UTC in seconds, used to get local time of the location.
+    /// Replaces former `timezone` timestamp, that carried no offset, so stored
+    /// locations without it are read as UTC
+    #[serde(default)]
+    utc_offset: i32,
 }
 
 impl Location {
-    pub async fn new(country: &str, region: &str, city: &str, timezone: DateTime<Utc>) -> Self {
-        Self::load(Uuid::new_v4(), country, region, city, timezone).await
+    pub async fn new(country: &str, region: &str, city: &str, utc_offset: i32) -> Self {
+        Self::load(Uuid::new_v4(), country, region, city, utc_offset).await
     }
 
-    pub async fn load(
-        id: Uuid,
-        country: &str,
-        region: &str,
-        city: &str,
-        timezone: DateTime<Utc>,
-    ) -> Self {
+    pub async fn load(id: Uuid, country: &str, region: &str, city: &str, utc_offset: i32) -> Self {
         Self {
             id,
             country: country.to_string(),
             region: region.to_string(),
             city: city.to_string(),
-            timezone,
+            utc_offset,
         }
     }
 }
@@ -42,15 +39,32 @@ impl Location {
 /// Import Logic: ``use <path>::<struct>::logic::*;``
 ///
 mod logic {
-    impl super::Location {}
+    use super::*;
+
+    impl super::Location {
+        /// Offset of the location, falls back to UTC if stored offset is out of range
+        pub async fn offset(&self) -> FixedOffset {
+            FixedOffset::east_opt(self.utc_offset).unwrap_or_else(|| Utc.fix())
+        }
+
+        /// Local time of the location at the given moment
+        pub async fn local_time(&self, at: DateTime<Utc>) -> NaiveTime {
+            at.with_timezone(&self.offset().await).time()
+        }
+
+        /// Local date of the location at the given moment
+        pub async fn local_date(&self, at: DateTime<Utc>) -> NaiveDate {
+            at.with_timezone(&self.offset().await).date_naive()
+        }
+    }
 }
 
 /// Boilerplate
 impl Location {
-    getter_ref!( { async } id: &Uuid, { async } country: &str, { async } region: &str, { async } city: &str, { async } timezone: &DateTime<Utc>);
+    getter_ref!( { async } id: &Uuid, { async } country: &str, { async } region: &str, { async } city: &str);
     getter_mut!( { async } id: &mut Uuid, { async } country: &mut String, { async } region: &mut String, { async } city: &mut String);
-    setter!( { async } id: Uuid, { async } country: &str, { async } region: &str, { async } city: &str );
-    getter!( { async } id: Uuid);
+    setter!( { async } id: Uuid, { async } country: &str, { async } region: &str, { async } city: &str, { async } utc_offset: i32 );
+    getter!( { async } id: Uuid, { async } utc_offset: i32);
 }
 
 impl HasId for Location {
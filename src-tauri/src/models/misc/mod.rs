@@ -1,2 +1,8 @@
+pub mod attachment;
+pub mod goods;
 pub mod location;
+pub mod queue;
+pub mod registration;
+pub mod signature;
+pub mod template;
 use crate::prelude::*;
@@ -0,0 +1,154 @@
+//! Queue of declarations ordered by priority, declarations of the same priority
+//! keep the order they were queued in
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+use uuid::Uuid;
+
+use crate::models::declaration::Priority;
+use crate::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+struct Entry {
+    priority: Priority,
+    seq: u64,
+    id: Uuid,
+}
+
+impl Ord for Entry {
+    /// Higher priority goes first, then the one queued earlier
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then(self.seq.cmp(&other.seq))
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DeclarationQueue {
+    entries: BTreeSet<Entry>,
+    index: HashMap<Uuid, Entry>,
+    next_seq: u64,
+}
+
+impl DeclarationQueue {
+    pub async fn new() -> Self {
+        Self::default()
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use uuid::Uuid;
+
+    use super::Entry;
+    use crate::models::declaration::Priority;
+
+    impl super::DeclarationQueue {
+        /// Queue declaration. Already queued declaration keeps its place among
+        /// declarations of the same priority, if priority has changed
+        pub async fn push(&mut self, id: Uuid, priority: Priority) {
+            let seq = match self.index.get(&id) {
+                Some(entry) => {
+                    self.entries.remove(entry);
+                    entry.seq
+                }
+                None => {
+                    self.next_seq += 1;
+                    self.next_seq
+                }
+            };
+            let entry = Entry { priority, seq, id };
+            self.entries.insert(entry);
+            self.index.insert(id, entry);
+        }
+
+        /// Take the most urgent declaration out of the queue
+        pub async fn pop(&mut self) -> Option<Uuid> {
+            let entry = *self.entries.iter().next()?;
+            self.entries.remove(&entry);
+            self.index.remove(&entry.id);
+
+            Some(entry.id)
+        }
+
+        pub async fn peek(&self) -> Option<Uuid> {
+            self.entries.iter().next().map(|entry| entry.id)
+        }
+
+        /// Returns true, if declaration was queued
+        pub async fn remove(&mut self, id: &Uuid) -> bool {
+            match self.index.remove(id) {
+                Some(entry) => self.entries.remove(&entry),
+                None => false,
+            }
+        }
+
+        /// Number of declarations ahead of the given one
+        pub async fn position(&self, id: &Uuid) -> Option<usize> {
+            let entry = self.index.get(id)?;
+
+            Some(self.entries.range(..entry).count())
+        }
+
+        pub async fn contains(&self, id: &Uuid) -> bool {
+            self.index.contains_key(id)
+        }
+
+        /// Queued declarations, the most urgent first
+        pub async fn ids(&self) -> Vec<Uuid> {
+            self.entries.iter().map(|entry| entry.id).collect()
+        }
+
+        pub async fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub async fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn priority_order() {
+        let mut queue = DeclarationQueue::new().await;
+        let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        queue.push(ids[0], Priority::Regular).await;
+        queue.push(ids[1], Priority::Perishable).await;
+        queue.push(ids[2], Priority::Regular).await;
+        queue.push(ids[3], Priority::LiveAnimals).await;
+
+        assert_eq!(queue.ids().await, vec![ids[3], ids[1], ids[0], ids[2]]);
+        assert_eq!(queue.position(&ids[0]).await, Some(2));
+
+        // Raising priority doesn't make declaration lose its place among equals
+        queue.push(ids[0], Priority::Perishable).await;
+        assert_eq!(queue.ids().await, vec![ids[3], ids[0], ids[1], ids[2]]);
+
+        assert!(queue.remove(&ids[3]).await);
+        assert!(!queue.remove(&ids[3]).await);
+        assert_eq!(queue.pop().await, Some(ids[0]));
+        assert_eq!(queue.len().await, 2);
+        assert!(!queue.contains(&ids[0]).await);
+    }
+}
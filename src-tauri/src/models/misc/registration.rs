@@ -0,0 +1,173 @@
+//! Registration numbers, that customs assigns to accepted declarations.
+//! Official format is `customs code/date/sequence`, e.g. `10702020/060923/0000042`
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::errors::customs::Err as CErr;
+use crate::errors::declaration::Err as DErr;
+use crate::prelude::*;
+
+/// Customs code is 8 digits long
+const CODE_LENGTH: usize = 8;
+/// Sequence is padded with zeroes to 7 digits
+const SEQUENCE_LENGTH: usize = 7;
+const DATE_FORMAT: &str = "%d%m%y";
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct RegistrationNumber {
+    customs_code: String,
+    date: NaiveDate,
+    sequence: u32,
+}
+
+impl RegistrationNumber {
+    pub async fn new(customs_code: &str, date: NaiveDate, sequence: u32) -> Self {
+        Self {
+            customs_code: customs_code.to_string(),
+            date,
+            sequence,
+        }
+    }
+}
+
+impl Display for RegistrationNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}/{:0width$}",
+            self.customs_code,
+            self.date.format(DATE_FORMAT),
+            self.sequence,
+            width = SEQUENCE_LENGTH
+        )
+    }
+}
+
+impl FromStr for RegistrationNumber {
+    type Err = DErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DErr::InvalidRegistrationNumber(s.to_string());
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        let [code, date, sequence] = parts[..] else {
+            return Err(invalid());
+        };
+        let is_digits =
+            |part: &str, len: usize| part.len() == len && part.chars().all(|c| c.is_ascii_digit());
+        if !is_digits(code, CODE_LENGTH) || !is_digits(sequence, SEQUENCE_LENGTH) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            customs_code: code.to_string(),
+            date: NaiveDate::parse_from_str(date, DATE_FORMAT).map_err(|_| invalid())?,
+            sequence: sequence.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Issues registration numbers of a single customs. Sequence starts anew every day,
+/// registry is kept with customs, so numbers are not reissued after restart
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Registry {
+    customs_code: String,
+    /// Day of the last issued number
+    date: Option<NaiveDate>,
+    /// Sequence of the last issued number
+    sequence: u32,
+}
+
+impl Registry {
+    /// Registry of customs with the given official code
+    pub async fn new(customs_code: &str) -> Result<Self, CErr> {
+        if customs_code.len() != CODE_LENGTH || !customs_code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(CErr::InvalidCode(customs_code.to_string()));
+        }
+
+        Ok(Self {
+            customs_code: customs_code.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use chrono::NaiveDate;
+
+    use super::RegistrationNumber;
+
+    impl super::Registry {
+        /// Next number of the day. Date is local date of customs
+        pub async fn issue(&mut self, date: NaiveDate) -> RegistrationNumber {
+            if self.date != Some(date) {
+                self.date = Some(date);
+                self.sequence = 0;
+            }
+            self.sequence += 1;
+
+            RegistrationNumber::new(&self.customs_code, date, self.sequence).await
+        }
+    }
+}
+
+/// Boilerplate
+impl RegistrationNumber {
+    getter_ref!( { async } customs_code: &str, { async } date: &NaiveDate);
+    getter!( { async } date: NaiveDate, { async } sequence: u32);
+}
+
+impl Registry {
+    getter_ref!( { async } customs_code: &str, { async } date: &Option<NaiveDate>);
+    getter!( { async } sequence: u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{RegistrationNumber, Registry};
+
+    #[tokio::test]
+    async fn issue() {
+        let mut registry = Registry::new("10702020").await.unwrap();
+        assert!(Registry::new("107020").await.is_err());
+        let day = |day| NaiveDate::from_ymd_opt(2023, 9, day).unwrap();
+
+        let first = registry.issue(day(6)).await;
+        assert_eq!(first.to_string(), "10702020/060923/0000001");
+        assert_eq!(
+            registry.issue(day(6)).await.to_string(),
+            "10702020/060923/0000002"
+        );
+        // Sequence starts anew the next day
+        assert_eq!(
+            registry.issue(day(7)).await.to_string(),
+            "10702020/070923/0000001"
+        );
+
+        assert_eq!(
+            "10702020/060923/0000001"
+                .parse::<RegistrationNumber>()
+                .unwrap(),
+            first
+        );
+        for invalid in [
+            "10702020/060923",
+            "1070202/060923/0000001",
+            "10702020/310223/0000001",
+            "10702020/060923/00000A1",
+        ] {
+            assert!(invalid.parse::<RegistrationNumber>().is_err());
+        }
+    }
+}
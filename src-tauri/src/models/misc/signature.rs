@@ -0,0 +1,148 @@
+//! Ed25519 key pairs of participants and detached signatures of declarations
+
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use uuid::Uuid;
+
+use crate::prelude::*;
+
+/// Public key of participant, hex encoded
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct PublicKey(String);
+
+/// Key pair of participant. Secret key is never serialized
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyPair {
+    signing_key: SigningKey,
+}
+
+impl std::fmt::Debug for KeyPair {
+    /// Only public key is shown, secret one must not get into logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field(
+                "public_key",
+                &to_hex(self.signing_key.verifying_key().as_bytes()),
+            )
+            .finish()
+    }
+}
+
+impl KeyPair {
+    pub async fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    /// Key pair from secret key, e.g. one kept by participant
+    pub async fn from_secret(secret: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret),
+        }
+    }
+}
+
+/// Detached signature of declaration, see `Declaration::canonical_bytes`
+#[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Signature {
+    signer: Uuid,
+    public_key: PublicKey,
+    /// Hex encoded Ed25519 signature
+    value: String,
+    signed_at: DateTime<Utc>,
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use super::*;
+
+    impl super::KeyPair {
+        pub async fn public_key(&self) -> PublicKey {
+            PublicKey(to_hex(self.signing_key.verifying_key().as_bytes()))
+        }
+
+        pub async fn secret(&self) -> [u8; 32] {
+            self.signing_key.to_bytes()
+        }
+
+        pub async fn sign(&self, signer: Uuid, message: &[u8]) -> Signature {
+            Signature {
+                signer,
+                public_key: self.public_key().await,
+                value: to_hex(&self.signing_key.sign(message).to_bytes()),
+                signed_at: Utc::now(),
+            }
+        }
+    }
+
+    impl super::Signature {
+        /// Whether message was signed with the key of signature.
+        /// Malformed key or signature never verifies
+        pub async fn verify(&self, message: &[u8]) -> bool {
+            let key = from_hex(&self.public_key.0)
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+            let value = from_hex(&self.value).and_then(|bytes| <[u8; 64]>::try_from(bytes).ok());
+
+            match (key, value) {
+                (Some(key), Some(value)) => key
+                    .verify(message, &ed25519_dalek::Signature::from_bytes(&value))
+                    .is_ok(),
+                _ => false,
+            }
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Boilerplate
+impl Signature {
+    getter_ref!( { async } public_key: &PublicKey, { async } value: &str);
+    getter!( { async } signer: Uuid, { async } signed_at: DateTime<Utc>);
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::KeyPair;
+
+    #[tokio::test]
+    async fn sign_verify() {
+        let keys = KeyPair::generate().await;
+        let signer = Uuid::new_v4();
+        let signature = keys.sign(signer, b"declaration").await;
+        assert!(signature.verify(b"declaration").await);
+        assert!(!signature.verify(b"declaration!").await);
+
+        let restored = KeyPair::from_secret(&keys.secret().await).await;
+        assert_eq!(restored.public_key().await, keys.public_key().await);
+        let other = KeyPair::generate().await;
+        assert_ne!(other.public_key().await, keys.public_key().await);
+        let mut forged = signature.clone();
+        forged.public_key = other.public_key().await;
+        assert!(!forged.verify(b"declaration").await);
+    }
+}
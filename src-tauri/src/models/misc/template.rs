@@ -0,0 +1,115 @@
+//! Templates of recurring declarations, e.g. regular shipments of the same goods
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::declaration::{Declaration, Draft};
+use crate::prelude::*;
+
+#[derive(Clone, Default, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct Template {
+    id: Uuid,
+    name: String,
+    /// Content new declarations are made of
+    declaration: Declaration<Draft>,
+    /// Fields cleared in the template, that have to be filled anew every time
+    blanked: Vec<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl Template {
+    /// Template from declaration in any state. Blanked fields are named
+    /// as in `Declaration::named_fields`, e.g. `goods[0].quantity`
+    #[tracing::instrument(skip(declaration))]
+    pub async fn new<State>(
+        name: &str,
+        declaration: &Declaration<State>,
+        blanked: &[&str],
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut declaration = declaration.to_draft().await;
+        declaration.blank(blanked).await?;
+        let id = Uuid::new_v4();
+        tracing::info!("Template {} '{}' created", id, name);
+
+        Ok(Self {
+            id,
+            name: name.to_string(),
+            declaration,
+            blanked: blanked.iter().map(ToString::to_string).collect(),
+            created_at: Utc::now(),
+        })
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use crate::models::declaration::{Declaration, Draft};
+
+    impl super::Template {
+        /// New draft made of the template, with fresh id and timestamps
+        pub async fn instantiate(&self) -> Declaration<Draft> {
+            self.declaration.to_draft().await
+        }
+    }
+}
+
+/// Boilerplate
+impl Template {
+    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } declaration: &Declaration<Draft>, { async } blanked: &Vec<String>);
+    getter_mut!( { async } declaration: &mut Declaration<Draft>);
+    setter!( { async } name: &str);
+    getter!( { async } id: Uuid, { async } created_at: DateTime<Utc>);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Template;
+    use crate::models::{
+        declaration::{Declaration, Draft, Pending},
+        misc::goods::GoodsItem,
+    };
+
+    #[tokio::test]
+    async fn instantiate() {
+        let mut decl = Declaration::<Draft>::new().await;
+        decl.set_sender_name("John")
+            .await
+            .set_receiver_name("Jane")
+            .await
+            .set_departure("Moscow")
+            .await
+            .set_destination("Kazan")
+            .await
+            .set_transport_type("Truck")
+            .await
+            .set_transport_name("Volvo")
+            .await;
+        let mut horse = GoodsItem::new("0101210000", "Horse", 2.0, "pcs").await;
+        horse.set_gross_weight(1000.0).await.set_value(5000.0).await;
+        decl.set_goods(vec![horse]).await;
+        let mut sent: Declaration<Pending> = decl.validate().await.unwrap();
+        sent.set_registration_region("Moscow").await;
+
+        let template = Template::new("Horses", &sent, &["goods[0].quantity", "departure"])
+            .await
+            .unwrap();
+        assert!(Template::new("Broken", &sent, &["colour"]).await.is_err());
+        let first = template.instantiate().await;
+        let second = template.instantiate().await;
+        assert_ne!(first.id().await, sent.id().await);
+        assert_ne!(first.id().await, second.id().await);
+        assert!(first.submitted_at().await.is_none());
+        assert!(first.revisions_ref().await.is_empty());
+        assert_eq!(first.sender_name_ref().await, "John");
+        assert_eq!(first.departure_ref().await, "");
+        assert_eq!(first.goods_ref().await[0].code_ref().await, "0101210000");
+        assert_eq!(first.goods_ref().await[0].quantity().await, 0.0);
+        assert!(first.validate().await.is_err());
+    }
+}
@@ -1,8 +1,11 @@
 use self::participants::declarant::logic::*;
 use self::participants::declarant::Declarant;
 
+pub mod classifier;
 pub mod customs;
 pub mod declaration;
+pub mod exchange;
 pub mod misc;
 pub mod participants;
 pub mod processor;
+pub mod validation;
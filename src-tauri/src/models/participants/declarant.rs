@@ -3,13 +3,15 @@ use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
-    models::{declaration::DeclarationGeneric, misc::location::Location},
+    models::{
+        declaration::{Billing, Declaration, DeclarationGeneric, Document, Draft, Tax},
+        misc::{location::Location, signature::KeyPair, template::Template},
+    },
     prelude::*,
     utils::HasId,
 };
 
 use crate::errors::declaration::Err as DErr;
-use crate::models::processor::Processor;
 
 use super::*;
 
@@ -23,6 +25,18 @@ pub struct Declarant {
     // #[serde(with = "Map<(String, DeclarationGeneric)>")]
     // #[serde_as(as = "Vec<(_, _)>")]
     declarations: HashMap<Uuid, DeclarationGeneric>,
+    /// Templates of recurring declarations
+    #[serde(default)]
+    templates: HashMap<Uuid, Template>,
+    /// Taxes charged on declarations, by their id
+    #[serde(default)]
+    taxes: HashMap<Uuid, Tax>,
+    /// Billings of declarations, by their id
+    #[serde(default)]
+    billings: HashMap<Uuid, Billing>,
+    /// Keys declarations are signed with on sending, if set
+    #[serde(skip)]
+    keys: Option<KeyPair>,
 }
 
 impl Declarant {
@@ -72,9 +86,16 @@ pub mod logic {
             match declaration {
                 DeclarationGeneric::Draft(decl) => {
                     tracing::debug!("Updating Declaration: {:?}", decl);
-                    Ok(self
-                        .declarations
-                        .insert(decl.id().await, declaration.clone()))
+                    let id = decl.id().await;
+                    // Edit of a draft is recorded as revision made by its owner
+                    if let Some(DeclarationGeneric::Draft(old)) = self.declarations.get(&id) {
+                        let mut decl = decl.clone();
+                        decl.revise(self.id, old).await;
+                        return Ok(self
+                            .declarations
+                            .insert(id, DeclarationGeneric::Draft(decl)));
+                    }
+                    Ok(self.declarations.insert(id, declaration.clone()))
                 }
                 DeclarationGeneric::Approved(decl) => {
                     tracing::debug!("Updating Declaration: {:?}", decl);
@@ -104,6 +125,12 @@ pub mod logic {
                         .declarations
                         .insert(decl.id().await, declaration.clone()))
                 }
+                DeclarationGeneric::InfoRequested(decl) => {
+                    tracing::debug!("Updating Declaration: {:?}", decl);
+                    Ok(self
+                        .declarations
+                        .insert(decl.id().await, declaration.clone()))
+                }
             }
         }
 
@@ -112,10 +139,10 @@ pub mod logic {
             self.declarations.get(&id)
         }
 
-        #[tracing::instrument]
-        async fn send_docs(
+        #[tracing::instrument(skip(proc))]
+        async fn send_docs<P: ProcessorLogic>(
             &mut self,
-            proc: &mut Processor,
+            proc: &mut P,
             id: Uuid,
         ) -> Result<(), Box<dyn Error>> {
             tracing::debug!("Sending Docs for Declaration: UUID={:?}", id);
@@ -128,8 +155,19 @@ pub mod logic {
                 );
             }
             let declaration = declaration.ok_or(DErr::DeclarationNotFound(id))?;
-            if let DeclarationGeneric::Draft(decl) = declaration {
-                let decl = decl.validate().await?;
+            if let DeclarationGeneric::Draft(draft) = declaration {
+                let mut draft = draft.clone();
+                // Region is signed along with the rest of content, so it goes first
+                if let Some(location) = &self.location {
+                    draft
+                        .set_registration_region(location.region_ref().await)
+                        .await;
+                }
+                if let Some(keys) = &self.keys {
+                    draft.sign(self.id, keys).await;
+                }
+                let mut decl = draft.validate().await?;
+                decl.revise(self.id, &draft).await;
                 proc.process_declaration(&decl).await?;
                 self.declarations
                     .insert(decl.id().await, DeclarationGeneric::Pending(decl));
@@ -143,15 +181,174 @@ pub mod logic {
                 Err(Box::new(DErr::IncorrectState(id, "Not Draft".to_string())))
             }
         }
+
+        #[tracing::instrument]
+        async fn amend_declaration(
+            &mut self,
+            id: Uuid,
+            field: &str,
+            value: &str,
+        ) -> Result<(), Box<dyn Error>> {
+            match self.declarations.get_mut(&id) {
+                Some(DeclarationGeneric::InfoRequested(decl)) => {
+                    decl.amend(field, value).await?;
+                    // Amendment invalidates the old signature
+                    if let Some(keys) = &self.keys {
+                        decl.sign(self.id, keys).await;
+                    }
+                    tracing::info!("Declaration UUID={} field {} amended", id, field);
+                    Ok(())
+                }
+                Some(_) => {
+                    tracing::error!("Declaration UUID={} is not in InfoRequested state", id);
+                    Err(Box::new(DErr::IncorrectState(
+                        id,
+                        "Not InfoRequested".to_string(),
+                    )))
+                }
+                None => Err(Box::new(DErr::DeclarationNotFound(id))),
+            }
+        }
+
+        #[tracing::instrument(skip(proc))]
+        async fn resubmit<P: ProcessorLogic>(
+            &mut self,
+            proc: &mut P,
+            id: Uuid,
+        ) -> Result<(), Box<dyn Error>> {
+            let declaration = self
+                .get_declaration(id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?;
+            let DeclarationGeneric::InfoRequested(decl) = declaration else {
+                tracing::error!("Declaration UUID={} is not in InfoRequested state", id);
+                return Err(Box::new(DErr::IncorrectState(
+                    id,
+                    "Not InfoRequested".to_string(),
+                )));
+            };
+            let decl = proc.resubmit(decl.clone()).await?;
+            self.declarations
+                .insert(id, DeclarationGeneric::Inspecting(decl));
+            tracing::info!(
+                "Declaration UUID={} status changed from InfoRequested to Inspecting",
+                id
+            );
+
+            Ok(())
+        }
+
+        #[tracing::instrument]
+        async fn save_template(
+            &mut self,
+            id: Uuid,
+            name: &str,
+            blanked: &[&str],
+        ) -> Result<Uuid, Box<dyn Error>> {
+            let decl = self
+                .get_declaration(id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?
+                .to_draft()
+                .await;
+            let template = Template::new(name, &decl, blanked).await?;
+            let template_id = template.id().await;
+            self.templates.insert(template_id, template);
+
+            Ok(template_id)
+        }
+
+        #[tracing::instrument]
+        async fn create_from_template(
+            &mut self,
+            template_id: Uuid,
+        ) -> Result<Uuid, Box<dyn Error>> {
+            let template = self
+                .templates
+                .get(&template_id)
+                .ok_or(DErr::TemplateNotFound(template_id))?;
+            let mut decl = template.instantiate().await;
+            decl.set_signed_by(self.id).await;
+
+            Ok(self.add_draft(decl).await)
+        }
+
+        #[tracing::instrument]
+        async fn clone_declaration(&mut self, id: Uuid) -> Result<Uuid, Box<dyn Error>> {
+            let mut decl = self
+                .get_declaration(id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?
+                .to_draft()
+                .await;
+            decl.set_signed_by(self.id).await;
+
+            Ok(self.add_draft(decl).await)
+        }
+
+        #[tracing::instrument(skip(self))]
+        async fn receive_docs(&mut self, doc: Document) -> Result<(), Box<dyn Error>> {
+            let declaration_id = doc.declaration_id().await;
+            if !matches!(doc, Document::Declaration(_))
+                && !self.declarations.contains_key(&declaration_id)
+            {
+                tracing::warn!("Document of unknown declaration {}", declaration_id);
+                return Err(Box::new(DErr::DeclarationNotFound(declaration_id)));
+            }
+            match doc {
+                Document::Declaration(decl) => {
+                    self.update_declaration(&decl).await?;
+                }
+                Document::Billing(billing) => {
+                    tracing::info!("Billing {} received", billing.id_ref().await);
+                    self.billings.insert(billing.id().await, billing);
+                }
+                Document::Tax(tax) => {
+                    tracing::info!("Tax {} received", tax.id_ref().await);
+                    self.taxes.insert(tax.id().await, tax);
+                }
+            }
+
+            Ok(())
+        }
+
+        async fn documents_of(&self, declaration_id: Uuid) -> Vec<Document> {
+            let mut docs = Vec::new();
+            for billing in self.billings.values() {
+                if *billing.declaration_id_ref().await == declaration_id {
+                    docs.push(Document::Billing(billing.clone()));
+                }
+            }
+            for tax in self.taxes.values() {
+                if *tax.declaration_id_ref().await == declaration_id {
+                    docs.push(Document::Tax(tax.clone()));
+                }
+            }
+
+            docs
+        }
     }
     impl Declarant {}
 }
 
+/// Private methods
+impl Declarant {
+    /// Keep new draft, returns its id
+    async fn add_draft(&mut self, decl: Declaration<Draft>) -> Uuid {
+        let id = decl.id().await;
+        self.declarations
+            .insert(id, DeclarationGeneric::Draft(decl));
+        tracing::info!("Draft UUID={} added to {}", id, self.id);
+
+        id
+    }
+}
+
 /// Boilerplate
 impl Declarant {
-    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } declarations: &HashMap<Uuid, DeclarationGeneric>, { async } location: &Option<Location>);
-    getter_mut!( { async } id: &mut Uuid, { async } name: &mut String, { async } declarations: &mut HashMap<Uuid, DeclarationGeneric>);
-    setter!( { async } id: Uuid, { async } name: &str, { async } declarations: HashMap<Uuid, DeclarationGeneric>);
+    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } declarations: &HashMap<Uuid, DeclarationGeneric>, { async } templates: &HashMap<Uuid, Template>, { async } taxes: &HashMap<Uuid, Tax>, { async } billings: &HashMap<Uuid, Billing>, { async } location: &Option<Location>, { async } keys: &Option<KeyPair>);
+    getter_mut!( { async } id: &mut Uuid, { async } name: &mut String, { async } declarations: &mut HashMap<Uuid, DeclarationGeneric>, { async } templates: &mut HashMap<Uuid, Template>);
+    setter!( { async } id: Uuid, { async } name: &str, { async } declarations: HashMap<Uuid, DeclarationGeneric>, { async } location: Option<Location>, { async } keys: Option<KeyPair>);
     getter!( { async } id: Uuid);
 }
 
@@ -161,8 +358,11 @@ mod tests {
 
     use crate::models::{
         customs::{logic::Logic, Customs},
-        declaration::{Declaration, DeclarationGeneric, Draft, Inspecting, Pending},
-        misc::location::Location,
+        declaration::{
+            Billing, Declaration, DeclarationGeneric, Document, Draft, GenericDowncast, Inspecting,
+            Pending, Tax,
+        },
+        misc::{location::Location, signature::KeyPair},
         processor::{logic::Logic as PLogic, Processor},
     };
 
@@ -175,11 +375,11 @@ mod tests {
             .await
             .set_id(Uuid::new_v4())
             .await
-            .set_departure("Test")
+            .set_departure("Test departure")
             .await
             .set_product_name("Test")
             .await
-            .set_product_code("Test")
+            .set_product_code("0101210000")
             .await
             .set_product_price(1.0)
             .await
@@ -308,7 +508,7 @@ mod tests {
     #[tokio::test]
     async fn send_docs() {
         let mut declarant = Declarant::new("John").await;
-        let mut declaration = generate_declaration().await;
+        let declaration = generate_declaration().await;
         let id = declaration.id_ref().await.clone();
         declarant
             .update_declaration(&DeclarationGeneric::Draft(declaration.clone()))
@@ -329,14 +529,130 @@ mod tests {
         assert_eq!(declarant.declarations.len(), 1);
         let mut name = String::new();
         let cumstoms = &processor.customs_ref().await[cumstoms.id_ref().await];
+        let held = cumstoms
+            .get_declaration(declaration.id_ref().await)
+            .await
+            .unwrap();
+        // Declaration is stamped, when it is submitted and when customs takes it,
+        // submission is recorded as revision made by declarant
+        let held_decl: &Declaration<Pending> = held.downcast().unwrap();
+        let revisions = held_decl.revisions_ref().await;
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].author().await, declarant.id().await);
+        assert_eq!(revisions[0].to_state_ref().await, "Pending");
+        assert!(held_decl.submitted_at().await.is_some());
+        // Everything else is sent as is
+        assert_eq!(held_decl.id().await, declaration.id().await);
+        assert_eq!(held_decl.signed_by().await, declaration.signed_by().await);
+        assert!(declaration.diff(held_decl).await.is_empty());
         assert_eq!(
-            cumstoms
-                .get_declaration(declaration.id_ref().await)
-                .await
-                .unwrap(),
-            DeclarationGeneric::Pending(declaration.into())
+            held_decl.attachments_ref().await,
+            declaration.attachments_ref().await
         );
     }
+
+    #[tokio::test]
+    async fn send_docs_registration_region() {
+        let mut declarant = Declarant::new("John").await;
+        let mut location = Location::default();
+        location.set_region("Moscow").await;
+        declarant.set_location(Some(location)).await;
+        declarant.set_keys(Some(KeyPair::generate().await)).await;
+        let declaration = generate_declaration().await;
+        let id = declaration.id().await;
+        declarant
+            .update_declaration(&DeclarationGeneric::Draft(declaration))
+            .await
+            .unwrap();
+
+        let mut processor = Processor::new().await;
+        let customs = Customs::new("Test", &Location::default()).await;
+        processor.connect(customs.clone()).await.unwrap();
+        declarant.send_docs(&mut processor, id).await.unwrap();
+
+        let customs = &processor.customs_ref().await[customs.id_ref().await];
+        let sent = customs.get_declaration(&id).await.unwrap();
+        let sent: &Declaration<Pending> = sent.downcast().unwrap();
+        assert_eq!(sent.registration_region_ref().await, "Moscow");
+        assert!(sent.verify_signature().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn templates() {
+        let mut declarant = Declarant::new("John").await;
+        let declaration = generate_declaration().await;
+        let id = declaration.id().await;
+        let pending: Declaration<Pending> = declaration.into();
+        declarant
+            .update_declaration(&DeclarationGeneric::Pending(pending))
+            .await
+            .unwrap();
+
+        let template_id = declarant
+            .save_template(id, "Regular", &["destination"])
+            .await
+            .unwrap();
+        assert!(declarant
+            .save_template(Uuid::new_v4(), "None", &[])
+            .await
+            .is_err());
+        let from_template = declarant.create_from_template(template_id).await.unwrap();
+        assert!(declarant
+            .create_from_template(Uuid::new_v4())
+            .await
+            .is_err());
+        let cloned = declarant.clone_declaration(id).await.unwrap();
+        assert_eq!(declarant.declarations.len(), 3);
+
+        let DeclarationGeneric::Draft(draft) = &declarant.declarations[&from_template] else {
+            panic!("Declaration made of template is not a draft");
+        };
+        assert_eq!(draft.destination_ref().await, "");
+        assert_eq!(draft.sender_name_ref().await, "John");
+        assert_eq!(draft.signed_by().await, declarant.id().await);
+        let DeclarationGeneric::Draft(draft) = &declarant.declarations[&cloned] else {
+            panic!("Cloned declaration is not a draft");
+        };
+        assert_eq!(draft.destination_ref().await, "Test");
+        assert!(draft.submitted_at().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn receive_docs() {
+        let mut declarant = Declarant::new("John").await;
+        let declaration = generate_declaration().await;
+        let id = declaration.id().await;
+        let mut tax = Tax::new();
+        tax.set_declaration_id(id).await.set_price(100.0).await;
+        assert!(declarant
+            .receive_docs(Document::Tax(tax.clone()))
+            .await
+            .is_err());
+
+        declarant
+            .receive_docs(Document::Declaration(DeclarationGeneric::Draft(
+                declaration,
+            )))
+            .await
+            .unwrap();
+        assert!(declarant.get_declaration(id).await.is_some());
+        let mut billing = Billing::new().await;
+        billing.set_declaration_id(id).await.set_price(50.0).await;
+        declarant
+            .receive_docs(Document::Tax(tax.clone()))
+            .await
+            .unwrap();
+        declarant
+            .receive_docs(Document::Billing(billing.clone()))
+            .await
+            .unwrap();
+
+        let docs = declarant.documents_of(id).await;
+        assert_eq!(docs.len(), 2);
+        assert!(docs.contains(&Document::Tax(tax)));
+        assert!(docs.contains(&Document::Billing(billing)));
+        assert!(declarant.documents_of(Uuid::new_v4()).await.is_empty());
+    }
 }
 
 impl HasId for Declarant {
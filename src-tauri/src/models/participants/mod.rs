@@ -5,8 +5,8 @@ use uuid::Uuid;
 
 use crate::prelude::*;
 
-use super::declaration::{Declaration, DeclarationGeneric};
-use super::processor::Processor;
+use super::declaration::{Declaration, DeclarationGeneric, Document};
+use super::processor::logic::Logic as ProcessorLogic;
 
 pub trait Participant {
     async fn update_declaration(
@@ -14,5 +14,41 @@ pub trait Participant {
         declaration: &DeclarationGeneric,
     ) -> Result<Option<DeclarationGeneric>, Box<dyn Error>>;
     async fn get_declaration(&self, id: Uuid) -> Option<&DeclarationGeneric>;
-    async fn send_docs(&mut self, proc: &mut Processor, id: Uuid) -> Result<(), Box<dyn Error>>;
+    /// Validate declaration and submit it through the given Processor,
+    /// which may be Processor itself or a handle to it
+    async fn send_docs<P: ProcessorLogic>(
+        &mut self,
+        proc: &mut P,
+        id: Uuid,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Amend field of declaration, that inspector asked information on
+    async fn amend_declaration(
+        &mut self,
+        id: Uuid,
+        field: &str,
+        value: &str,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Send amended declaration back to inspector, who asked information on it,
+    /// through the given Processor, which may be Processor itself or a handle to it
+    async fn resubmit<P: ProcessorLogic>(
+        &mut self,
+        proc: &mut P,
+        id: Uuid,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Save declaration as a template, blanked fields are cleared in it. Returns template id
+    async fn save_template(
+        &mut self,
+        id: Uuid,
+        name: &str,
+        blanked: &[&str],
+    ) -> Result<Uuid, Box<dyn Error>>;
+    /// New draft made of the template. Returns id of the draft
+    async fn create_from_template(&mut self, template_id: Uuid) -> Result<Uuid, Box<dyn Error>>;
+    /// New draft with the content of declaration in any state. Returns id of the draft
+    async fn clone_declaration(&mut self, id: Uuid) -> Result<Uuid, Box<dyn Error>>;
+    /// Takes any document. Declaration is updated as by `update_declaration`,
+    /// billings and taxes are kept only for declarations participant has
+    async fn receive_docs(&mut self, doc: Document) -> Result<(), Box<dyn Error>>;
+    /// Billings and taxes, that belong to declaration with provided UUID
+    async fn documents_of(&self, declaration_id: Uuid) -> Vec<Document>;
 }
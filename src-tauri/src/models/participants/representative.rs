@@ -4,8 +4,8 @@ use uuid::Uuid;
 
 use crate::{
     models::{
-        declaration::{Declaration, DeclarationGeneric},
-        misc::location::Location,
+        declaration::{Billing, Declaration, DeclarationGeneric, Draft, Tax},
+        misc::{location::Location, signature::KeyPair, template::Template},
     },
     prelude::*,
     utils::HasId,
@@ -20,11 +20,23 @@ pub struct Representative {
     name: String,
     location: Option<Location>,
     declarations: HashMap<Uuid, DeclarationGeneric>,
+    /// Templates of recurring declarations
+    #[serde(default)]
+    templates: HashMap<Uuid, Template>,
+    /// Taxes charged on declarations, by their id
+    #[serde(default)]
+    taxes: HashMap<Uuid, Tax>,
+    /// Billings of declarations, by their id
+    #[serde(default)]
+    billings: HashMap<Uuid, Billing>,
     service_requests: HashMap<Uuid, ServiceRequest>,
     service_prices: [f64; 3],
     #[serde(skip)]
     clients: HashMap<Uuid, Arc<RwLock<Client>>>,
     brokerage_account: f64,
+    /// Keys declarations are signed with on sending, if set
+    #[serde(skip)]
+    keys: Option<KeyPair>,
 }
 
 impl Representative {
@@ -61,7 +73,6 @@ pub mod logic {
     use crate::models::participants::client::logic::Logic as CLogic;
     use crate::models::participants::Participant;
     use crate::models::processor::logic::Logic as ProcessorLogic;
-    use crate::models::processor::Processor;
     pub trait Logic: Participant {
         /// Stub method for billing system.
         /// Just increase brokerage_account value
@@ -105,15 +116,21 @@ pub mod logic {
             let client = self
                 .get_client(&client_id)
                 .await
-                .ok_or_else(|| RErr::ClientNotFound(client_id))?;
+                .ok_or_else(|| RErr::ClientNotFound(client_id))?
+                .clone();
 
-            match client.write() {
-                Ok(mut w_lock) => {
-                    w_lock.receive_billing(billing.clone()).await;
-                    Ok(())
-                }
-                Err(_) => Err(Box::new(RErr::ClientWriteLocked(client_id))),
-            }
+            // Lock guard can't be held across await, so billing goes to a copy of client,
+            // that replaces it afterwards
+            let mut updated = client
+                .read()
+                .map_err(|_| RErr::ClientReadLocked(client_id))?
+                .clone();
+            updated.receive_billing(billing).await?;
+            *client
+                .write()
+                .map_err(|_| RErr::ClientWriteLocked(client_id))? = updated;
+
+            Ok(())
         }
 
         async fn get_client(&mut self, id: &Uuid) -> Option<&Arc<RwLock<Client>>> {
@@ -141,9 +158,16 @@ pub mod logic {
             match declaration {
                 DeclarationGeneric::Draft(decl) => {
                     tracing::debug!("Updating Declaration: {:?}", decl);
-                    Ok(self
-                        .declarations
-                        .insert(decl.id().await, declaration.clone()))
+                    let id = decl.id().await;
+                    // Edit of a draft is recorded as revision made by its owner
+                    if let Some(DeclarationGeneric::Draft(old)) = self.declarations.get(&id) {
+                        let mut decl = decl.clone();
+                        decl.revise(self.id, old).await;
+                        return Ok(self
+                            .declarations
+                            .insert(id, DeclarationGeneric::Draft(decl)));
+                    }
+                    Ok(self.declarations.insert(id, declaration.clone()))
                 }
                 DeclarationGeneric::Approved(decl) => {
                     tracing::debug!("Updating Declaration: {:?}", decl);
@@ -173,6 +197,12 @@ pub mod logic {
                         .declarations
                         .insert(decl.id().await, declaration.clone()))
                 }
+                DeclarationGeneric::InfoRequested(decl) => {
+                    tracing::debug!("Updating Declaration: {:?}", decl);
+                    Ok(self
+                        .declarations
+                        .insert(decl.id().await, declaration.clone()))
+                }
             }
         }
 
@@ -181,10 +211,10 @@ pub mod logic {
             self.declarations.get(&id)
         }
 
-        #[tracing::instrument]
-        async fn send_docs(
+        #[tracing::instrument(skip(proc))]
+        async fn send_docs<P: ProcessorLogic>(
             &mut self,
-            proc: &mut Processor,
+            proc: &mut P,
             id: Uuid,
         ) -> Result<(), Box<dyn Error>> {
             tracing::debug!("Sending Docs for Declaration: UUID={:?}", id);
@@ -197,8 +227,19 @@ pub mod logic {
                 );
             }
             let declaration = declaration.ok_or(DErr::DeclarationNotFound(id))?;
-            if let DeclarationGeneric::Draft(decl) = declaration {
-                let decl = decl.validate().await?;
+            if let DeclarationGeneric::Draft(draft) = declaration {
+                let mut draft = draft.clone();
+                // Region is signed along with the rest of content, so it goes first
+                if let Some(location) = &self.location {
+                    draft
+                        .set_registration_region(location.region_ref().await)
+                        .await;
+                }
+                if let Some(keys) = &self.keys {
+                    draft.sign(self.id, keys).await;
+                }
+                let mut decl = draft.validate().await?;
+                decl.revise(self.id, &draft).await;
                 proc.process_declaration(&decl).await?;
                 self.declarations
                     .insert(decl.id().await, DeclarationGeneric::Pending(decl));
@@ -212,14 +253,174 @@ pub mod logic {
                 Err(Box::new(DErr::IncorrectState(id, "Not Draft".to_string())))
             }
         }
+
+        #[tracing::instrument]
+        async fn amend_declaration(
+            &mut self,
+            id: Uuid,
+            field: &str,
+            value: &str,
+        ) -> Result<(), Box<dyn Error>> {
+            match self.declarations.get_mut(&id) {
+                Some(DeclarationGeneric::InfoRequested(decl)) => {
+                    decl.amend(field, value).await?;
+                    // Amendment invalidates the old signature
+                    if let Some(keys) = &self.keys {
+                        decl.sign(self.id, keys).await;
+                    }
+                    tracing::info!("Declaration UUID={} field {} amended", id, field);
+                    Ok(())
+                }
+                Some(_) => {
+                    tracing::error!("Declaration UUID={} is not in InfoRequested state", id);
+                    Err(Box::new(DErr::IncorrectState(
+                        id,
+                        "Not InfoRequested".to_string(),
+                    )))
+                }
+                None => Err(Box::new(DErr::DeclarationNotFound(id))),
+            }
+        }
+
+        #[tracing::instrument(skip(proc))]
+        async fn resubmit<P: ProcessorLogic>(
+            &mut self,
+            proc: &mut P,
+            id: Uuid,
+        ) -> Result<(), Box<dyn Error>> {
+            let declaration = self
+                .get_declaration(id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?;
+            let DeclarationGeneric::InfoRequested(decl) = declaration else {
+                tracing::error!("Declaration UUID={} is not in InfoRequested state", id);
+                return Err(Box::new(DErr::IncorrectState(
+                    id,
+                    "Not InfoRequested".to_string(),
+                )));
+            };
+            let decl = proc.resubmit(decl.clone()).await?;
+            self.declarations
+                .insert(id, DeclarationGeneric::Inspecting(decl));
+            tracing::info!(
+                "Declaration UUID={} status changed from InfoRequested to Inspecting",
+                id
+            );
+
+            Ok(())
+        }
+
+        #[tracing::instrument]
+        async fn save_template(
+            &mut self,
+            id: Uuid,
+            name: &str,
+            blanked: &[&str],
+        ) -> Result<Uuid, Box<dyn Error>> {
+            let decl = self
+                .get_declaration(id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?
+                .to_draft()
+                .await;
+            let template = Template::new(name, &decl, blanked).await?;
+            let template_id = template.id().await;
+            self.templates.insert(template_id, template);
+
+            Ok(template_id)
+        }
+
+        #[tracing::instrument]
+        async fn create_from_template(
+            &mut self,
+            template_id: Uuid,
+        ) -> Result<Uuid, Box<dyn Error>> {
+            let template = self
+                .templates
+                .get(&template_id)
+                .ok_or(DErr::TemplateNotFound(template_id))?;
+            let mut decl = template.instantiate().await;
+            decl.set_signed_by(self.id).await;
+
+            Ok(self.add_draft(decl).await)
+        }
+
+        #[tracing::instrument]
+        async fn clone_declaration(&mut self, id: Uuid) -> Result<Uuid, Box<dyn Error>> {
+            let mut decl = self
+                .get_declaration(id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?
+                .to_draft()
+                .await;
+            decl.set_signed_by(self.id).await;
+
+            Ok(self.add_draft(decl).await)
+        }
+
+        #[tracing::instrument(skip(self))]
+        async fn receive_docs(&mut self, doc: Document) -> Result<(), Box<dyn Error>> {
+            let declaration_id = doc.declaration_id().await;
+            if !matches!(doc, Document::Declaration(_))
+                && !self.declarations.contains_key(&declaration_id)
+            {
+                tracing::warn!("Document of unknown declaration {}", declaration_id);
+                return Err(Box::new(DErr::DeclarationNotFound(declaration_id)));
+            }
+            match doc {
+                Document::Declaration(decl) => {
+                    self.update_declaration(&decl).await?;
+                }
+                Document::Billing(billing) => {
+                    tracing::info!("Billing {} received", billing.id_ref().await);
+                    self.billings.insert(billing.id().await, billing);
+                }
+                Document::Tax(tax) => {
+                    tracing::info!("Tax {} received", tax.id_ref().await);
+                    self.taxes.insert(tax.id().await, tax);
+                }
+            }
+
+            Ok(())
+        }
+
+        async fn documents_of(&self, declaration_id: Uuid) -> Vec<Document> {
+            let mut docs = Vec::new();
+            for billing in self.billings.values() {
+                if *billing.declaration_id_ref().await == declaration_id {
+                    docs.push(Document::Billing(billing.clone()));
+                }
+            }
+            for tax in self.taxes.values() {
+                if *tax.declaration_id_ref().await == declaration_id {
+                    docs.push(Document::Tax(tax.clone()));
+                }
+            }
+
+            docs
+        }
     }
     impl Representative {}
 }
+
+/// Private methods
+impl Representative {
+    /// Keep new draft, returns its id
+    async fn add_draft(&mut self, decl: Declaration<Draft>) -> Uuid {
+        let id = decl.id().await;
+        self.declarations
+            .insert(id, DeclarationGeneric::Draft(decl));
+        tracing::info!("Draft UUID={} added to {}", id, self.id);
+
+        id
+    }
+}
+
 /// Boilerplate
 impl Representative {
-    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } declarations: &HashMap<Uuid, DeclarationGeneric>, { async } service_requests: &HashMap<Uuid, ServiceRequest>, { async } service_prices: &[f64; 3]);
-    getter_mut!( { async } id: &mut Uuid, { async } name: &mut String, { async } declarations: &mut HashMap<Uuid, DeclarationGeneric>, { async } service_requests: &mut HashMap<Uuid,ServiceRequest>,  { async } service_prices: &mut [f64; 3]);
-    setter!( { async } id: Uuid, { async } name: &str, { async } declarations: HashMap<Uuid, DeclarationGeneric>, { async } service_requests: HashMap<Uuid, ServiceRequest>, { async } service_prices: [f64; 3]);
+    getter_ref!( { async } id: &Uuid, { async } name: &str, { async } declarations: &HashMap<Uuid, DeclarationGeneric>, { async } templates: &HashMap<Uuid, Template>, { async } taxes: &HashMap<Uuid, Tax>, { async } billings: &HashMap<Uuid, Billing>, { async } service_requests: &HashMap<Uuid, ServiceRequest>, { async } service_prices: &[f64; 3], { async } keys: &Option<KeyPair>);
+    getter_mut!( { async } id: &mut Uuid, { async } name: &mut String, { async } declarations: &mut HashMap<Uuid, DeclarationGeneric>, { async } templates: &mut HashMap<Uuid, Template>, { async } service_requests: &mut HashMap<Uuid,ServiceRequest>,  { async } service_prices: &mut [f64; 3]);
+    setter!( { async } id: Uuid, { async } name: &str, { async } declarations: HashMap<Uuid, DeclarationGeneric>, { async } service_requests: HashMap<Uuid, ServiceRequest>, { async } service_prices: [f64; 3], { async } location: Option<Location>, { async } keys: Option<KeyPair>);
     getter!( { async } id: Uuid);
 }
 
@@ -239,6 +440,26 @@ mod tests {
         assert!(repr.brokerage_account - 1000.0 < f64::EPSILON);
     }
 
+    #[tokio::test]
+    async fn request_pay() {
+        let mut repr = super::Representative::new("Test").await;
+        let client = Client::new("Test").await;
+        let service = ServiceRequest {
+            id: Uuid::new_v4(),
+            client: client.id().await,
+            description: "Test".to_string(),
+            service: Service::Consultation,
+        };
+        let service_id = service.id;
+        repr.receive_service(service).await.unwrap();
+        assert!(repr.request_pay(&service_id).await.is_err());
+
+        repr.clients
+            .insert(client.id().await, Arc::new(RwLock::new(client)));
+        repr.request_pay(&service_id).await.unwrap();
+        assert!(repr.request_pay(&Uuid::new_v4()).await.is_err());
+    }
+
     #[tokio::test]
     async fn receive_service() {
         let mut repr = super::Representative::new("Test").await;
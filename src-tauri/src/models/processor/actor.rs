@@ -0,0 +1,994 @@
+//! Processor running as an async actor.
+//!
+//! Processor lives inside its own task and owns all the customs, participants talk to it
+//! through cloneable [`ProcessorHandle`], so nobody needs exclusive access to the Processor.
+//! Handle implements Processor's `Logic`, so it can be used wherever Processor is expected.
+//!
+//! Owner of declaration lives outside the actor, so inspector's decisions and requests
+//! for information are made inside it on owner's copy of declaration, and the handle
+//! delivers them to the owner afterwards
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use super::{
+    record::{DispatchRecord, Reason},
+    Dispatch, Located, Processor, Relocation, Submitted,
+};
+use crate::errors::{channel::Err as ChErr, into_send};
+use crate::models::{
+    customs::Customs,
+    declaration::{
+        Approved, Declaration, DeclarationGeneric, InfoRequested, Inspecting, Pending, Rejected,
+    },
+    misc::registration::RegistrationNumber,
+    participants::{declarant::Declarant, Participant},
+};
+use crate::prelude::*;
+
+/// Requests, that can be queued before senders have to wait for Processor
+const CHANNEL_CAPACITY: usize = 64;
+/// How often actor looks for declarations, that exceeded Processor's SLA
+const SLA_CHECK_PERIOD: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Errors are made sendable with `into_send`, so handle's callers can match them
+type Reply<T> = oneshot::Sender<Result<T, Box<dyn Error + Send + Sync>>>;
+
+#[derive(Debug)]
+enum Command {
+    Connect {
+        customs: Box<Customs>,
+        reply: Reply<Option<Customs>>,
+    },
+    ProcessDeclaration {
+        decl: Declaration<Pending>,
+        reply: Reply<Dispatch>,
+    },
+    Enqueue {
+        decl: Declaration<Pending>,
+        reply: Reply<usize>,
+    },
+    DispatchQueued {
+        reply: oneshot::Sender<Vec<(Uuid, Option<Dispatch>)>>,
+    },
+    AssignNext {
+        customs_id: Uuid,
+        inspector_id: Uuid,
+        reply: Reply<Option<Uuid>>,
+    },
+    FindDeclaration {
+        id: Uuid,
+        reply: oneshot::Sender<Option<Located>>,
+    },
+    FindByNumber {
+        number: RegistrationNumber,
+        reply: oneshot::Sender<Option<Located>>,
+    },
+    RemoveDeclaration {
+        id: Uuid,
+        reply: oneshot::Sender<Option<DeclarationGeneric>>,
+    },
+    CustomsLoad {
+        reply: oneshot::Sender<HashMap<Uuid, f64>>,
+    },
+    DispatchHistory {
+        declaration_id: Uuid,
+        reply: oneshot::Sender<Vec<DispatchRecord>>,
+    },
+    Drain {
+        id: Uuid,
+        reply: Reply<Vec<Relocation>>,
+    },
+    Resume {
+        id: Uuid,
+        reply: Reply<()>,
+    },
+    Disconnect {
+        id: Uuid,
+        reply: Reply<(Customs, Vec<Relocation>)>,
+    },
+    RedispatchStale {
+        at: DateTime<Utc>,
+        reply: oneshot::Sender<Vec<Relocation>>,
+    },
+    Link {
+        peer: ProcessorHandle,
+        reply: oneshot::Sender<Option<ProcessorHandle>>,
+    },
+    Unlink {
+        peer_id: Uuid,
+        reply: oneshot::Sender<Option<ProcessorHandle>>,
+    },
+    ForwardDeclaration {
+        decl: Declaration<Pending>,
+        visited: Vec<Uuid>,
+        reply: Reply<Dispatch>,
+    },
+    FindInFederation {
+        id: Uuid,
+        visited: Vec<Uuid>,
+        reply: oneshot::Sender<Option<Located>>,
+    },
+    /// `held` is owner's copy of declaration, None if owner has none
+    Approve {
+        id: Uuid,
+        held: Option<Box<DeclarationGeneric>>,
+        reason: String,
+        reply: Reply<Declaration<Approved>>,
+    },
+    Reject {
+        id: Uuid,
+        held: Option<Box<DeclarationGeneric>>,
+        reason: String,
+        reply: Reply<Declaration<Rejected>>,
+    },
+    RequestInfo {
+        id: Uuid,
+        held: Option<Box<DeclarationGeneric>>,
+        fields: Vec<String>,
+        message: String,
+        reply: Reply<Declaration<InfoRequested>>,
+    },
+    Resubmit {
+        declaration: Box<Declaration<InfoRequested>>,
+        reply: Reply<Declaration<Inspecting>>,
+    },
+    /// Sent by actor to itself, once a peer took declaration, see `settle`
+    NoteForwarded {
+        record_id: Uuid,
+        dispatch: Dispatch,
+    },
+    Shutdown {
+        reply: oneshot::Sender<Processor>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessorHandle {
+    /// Id of the Processor behind the handle, known without asking the actor
+    id: Uuid,
+    sender: mpsc::Sender<Command>,
+}
+
+/// Handle, that doesn't keep actor alive, see `ProcessorHandle::downgrade`
+#[derive(Debug, Clone)]
+pub struct WeakProcessorHandle {
+    id: Uuid,
+    sender: mpsc::WeakSender<Command>,
+}
+
+impl ProcessorHandle {
+    /// Move Processor into its own task and return handle to it.
+    /// Actor stops, when every handle is dropped or on shutdown
+    pub async fn spawn(processor: Processor) -> Self {
+        Self::spawn_with_sla_check(processor, SLA_CHECK_PERIOD).await
+    }
+
+    /// Same as `spawn`, but looks for declarations, that exceeded SLA, every `period`
+    pub async fn spawn_with_sla_check(processor: Processor, period: std::time::Duration) -> Self {
+        let id = processor.id().await;
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        tokio::spawn(logic::run(processor, receiver, sender.downgrade()));
+        tokio::spawn(logic::tick(sender.downgrade(), period));
+
+        Self { id, sender }
+    }
+
+    /// Handle, that can reach the actor only while some other handle keeps it alive
+    pub async fn downgrade(&self) -> WeakProcessorHandle {
+        WeakProcessorHandle {
+            id: self.id,
+            sender: self.sender.downgrade(),
+        }
+    }
+
+    /// Stop the actor and take Processor back. Other handles
+    /// get `ChannelWasClosed` afterwards
+    pub async fn shutdown(self) -> Result<Processor, Box<dyn Error>> {
+        Ok(self.request(|reply| Command::Shutdown { reply }).await?)
+    }
+
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> Command,
+    ) -> Result<T, ChErr> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(command(reply))
+            .await
+            .map_err(|_| ChErr::ChannelWasClosed)?;
+
+        response.await.map_err(|_| ChErr::FailedToReceive)
+    }
+
+    getter!( { async } id: Uuid);
+
+    /// Same as `request`, but for commands, that may fail on Processor's side
+    async fn try_request<T>(
+        &self,
+        command: impl FnOnce(Reply<T>) -> Command,
+    ) -> Result<T, Box<dyn Error>> {
+        self.request(command)
+            .await?
+            .map_err(|err| -> Box<dyn Error> { err })
+    }
+}
+
+impl WeakProcessorHandle {
+    /// None, if actor has stopped
+    pub async fn upgrade(&self) -> Option<ProcessorHandle> {
+        Some(ProcessorHandle {
+            id: self.id,
+            sender: self.sender.upgrade()?,
+        })
+    }
+
+    getter!( { async } id: Uuid);
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+pub mod logic {
+    use super::*;
+    pub use crate::models::processor::logic::Logic;
+
+    /// Actor loop, handles commands one by one until every handle is gone.
+    /// `actor` is actor's own sender, weak, so it doesn't keep actor alive
+    pub(super) async fn run(
+        mut processor: Processor,
+        mut receiver: mpsc::Receiver<Command>,
+        actor: mpsc::WeakSender<Command>,
+    ) {
+        tracing::info!("Processor actor started");
+        while let Some(command) = receiver.recv().await {
+            if !handle(&mut processor, command, &actor).await {
+                tracing::info!("Processor actor shut down");
+                return;
+            }
+        }
+        tracing::info!("Every Processor handle is dropped, actor stopped");
+    }
+
+    /// Ask actor to re-dispatch stale declarations every `period`.
+    /// Sender is weak, so ticking doesn't keep actor alive
+    pub(super) async fn tick(sender: mpsc::WeakSender<Command>, period: std::time::Duration) {
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let Some(sender) = sender.upgrade() else {
+                return;
+            };
+            let (reply, _) = oneshot::channel();
+            let command = Command::RedispatchStale {
+                at: Utc::now(),
+                reply,
+            };
+            if sender.send(command).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Returns false, if actor should stop
+    async fn handle(
+        processor: &mut Processor,
+        command: Command,
+        actor: &mpsc::WeakSender<Command>,
+    ) -> bool {
+        // Requester might have given up waiting, so failed replies are ignored
+        match command {
+            Command::Connect { customs, reply } => {
+                let res = processor.connect(*customs).await.map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::ProcessDeclaration { decl, reply } => {
+                let submitted = processor
+                    .submit_locally(&decl, Reason::Submitted, &[])
+                    .await;
+                settle(submitted, decl, reply, actor.clone());
+            }
+            Command::Enqueue { decl, reply } => {
+                let res = processor.enqueue(decl).await.map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::DispatchQueued { reply } => {
+                let _ = reply.send(processor.dispatch_queued().await);
+            }
+            Command::AssignNext {
+                customs_id,
+                inspector_id,
+                reply,
+            } => {
+                let res = processor
+                    .assign_next(customs_id, inspector_id)
+                    .await
+                    .map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::FindDeclaration { id, reply } => {
+                search(processor, id, &[], reply).await;
+            }
+            Command::FindByNumber { number, reply } => {
+                let _ = reply.send(processor.find_by_number(&number).await);
+            }
+            Command::RemoveDeclaration { id, reply } => {
+                let _ = reply.send(processor.remove_declaration(id).await);
+            }
+            Command::CustomsLoad { reply } => {
+                let _ = reply.send(processor.customs_load().await);
+            }
+            Command::DispatchHistory {
+                declaration_id,
+                reply,
+            } => {
+                let _ = reply.send(processor.dispatch_history(declaration_id).await);
+            }
+            Command::Drain { id, reply } => {
+                let res = processor.drain(id).await.map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::Resume { id, reply } => {
+                let res = processor.resume(id).await.map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::Disconnect { id, reply } => {
+                let res = processor.disconnect(id).await.map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::RedispatchStale { at, reply } => {
+                let _ = reply.send(processor.redispatch_stale(at).await);
+            }
+            Command::Link { peer, reply } => {
+                let _ = reply.send(processor.link(peer).await);
+            }
+            Command::Unlink { peer_id, reply } => {
+                let _ = reply.send(processor.unlink(peer_id).await);
+            }
+            Command::ForwardDeclaration {
+                decl,
+                visited,
+                reply,
+            } => {
+                let from = visited.last().copied().unwrap_or_default();
+                let submitted = processor
+                    .submit_locally(&decl, Reason::Forwarded(from), &visited)
+                    .await;
+                settle(submitted, decl, reply, actor.clone());
+            }
+            Command::FindInFederation { id, visited, reply } => {
+                search(processor, id, &visited, reply).await;
+            }
+            Command::NoteForwarded {
+                record_id,
+                dispatch,
+            } => {
+                processor.note_forwarded(record_id, dispatch).await;
+            }
+            Command::Approve {
+                id,
+                held,
+                reason,
+                reply,
+            } => {
+                let mut owner = stand_in(held).await;
+                let res = processor
+                    .approve(&mut owner, id, &reason)
+                    .await
+                    .map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::Reject {
+                id,
+                held,
+                reason,
+                reply,
+            } => {
+                let mut owner = stand_in(held).await;
+                let res = processor
+                    .reject(&mut owner, id, &reason)
+                    .await
+                    .map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::RequestInfo {
+                id,
+                held,
+                fields,
+                message,
+                reply,
+            } => {
+                let mut owner = stand_in(held).await;
+                let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+                let res = processor
+                    .request_info(&mut owner, id, &fields, &message)
+                    .await
+                    .map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::Resubmit { declaration, reply } => {
+                let res = processor.resubmit(*declaration).await.map_err(into_send);
+                let _ = reply.send(res);
+            }
+            Command::Shutdown { reply } => {
+                let _ = reply.send(std::mem::take(processor));
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Reply, if declaration was dispatched here. Otherwise peers are asked in a separate task,
+    /// so peers, that forward to each other at the same time, don't wait for each other
+    fn settle(
+        submitted: Submitted,
+        decl: Declaration<Pending>,
+        reply: Reply<Dispatch>,
+        actor: mpsc::WeakSender<Command>,
+    ) {
+        match submitted {
+            Submitted::Done(res) => {
+                let _ = reply.send(res);
+            }
+            Submitted::Forward {
+                record_id,
+                peers,
+                visited,
+            } => {
+                tokio::spawn(async move {
+                    let res = Processor::forward_to_peers(&decl, peers, &visited).await;
+                    if let (Ok(dispatch), Some(actor)) = (&res, actor.upgrade()) {
+                        // Goes before reply, so requester sees the record updated
+                        let command = Command::NoteForwarded {
+                            record_id,
+                            dispatch: *dispatch,
+                        };
+                        let _ = actor.send(command).await;
+                    }
+                    let _ = reply.send(res);
+                });
+            }
+        }
+    }
+
+    /// Look declaration up here, peers are asked in a separate task, as in `settle`
+    async fn search(
+        processor: &Processor,
+        id: Uuid,
+        visited: &[Uuid],
+        reply: oneshot::Sender<Option<Located>>,
+    ) {
+        if let Some(found) = processor.find_locally(id).await {
+            let _ = reply.send(Some(found));
+            return;
+        }
+        let (peers, visited) = processor.peers_to_ask(visited).await;
+        tokio::spawn(async move {
+            let _ = reply.send(Processor::find_at_peers(id, peers, &visited).await);
+        });
+    }
+
+    /// Participant standing in for the owner of declaration inside the actor,
+    /// holds owner's copy of it, so inspector sees what owner has
+    async fn stand_in(held: Option<Box<DeclarationGeneric>>) -> Declarant {
+        let mut owner = Declarant::default();
+        if let Some(held) = held {
+            // Declarant takes declaration in any state
+            let _ = owner.update_declaration(&held).await;
+        }
+
+        owner
+    }
+
+    /// Owner's copy of declaration, that goes to the actor along with decision on it
+    async fn held_by<P: Participant>(owner: &P, id: Uuid) -> Option<Box<DeclarationGeneric>> {
+        owner.get_declaration(id).await.cloned().map(Box::new)
+    }
+
+    impl Logic for ProcessorHandle {
+        async fn connect(&mut self, customs: Customs) -> Result<Option<Customs>, Box<dyn Error>> {
+            let customs = Box::new(customs);
+            self.try_request(|reply| Command::Connect { customs, reply })
+                .await
+        }
+
+        async fn process_declaration(
+            &mut self,
+            decl: &Declaration<Pending>,
+        ) -> Result<Dispatch, Box<dyn Error>> {
+            let decl = decl.clone();
+            self.try_request(|reply| Command::ProcessDeclaration { decl, reply })
+                .await
+        }
+
+        async fn enqueue(&mut self, decl: Declaration<Pending>) -> Result<usize, Box<dyn Error>> {
+            self.try_request(|reply| Command::Enqueue { decl, reply })
+                .await
+        }
+
+        async fn dispatch_queued(&mut self) -> Vec<(Uuid, Option<Dispatch>)> {
+            self.request(|reply| Command::DispatchQueued { reply })
+                .await
+                .unwrap_or_default()
+        }
+
+        async fn assign_next(
+            &mut self,
+            customs_id: Uuid,
+            inspector_id: Uuid,
+        ) -> Result<Option<Uuid>, Box<dyn Error>> {
+            self.try_request(|reply| Command::AssignNext {
+                customs_id,
+                inspector_id,
+                reply,
+            })
+            .await
+        }
+
+        async fn find_declaration(&self, id: Uuid) -> Option<Located> {
+            self.request(|reply| Command::FindDeclaration { id, reply })
+                .await
+                .ok()
+                .flatten()
+        }
+
+        async fn find_by_number(&self, number: &RegistrationNumber) -> Option<Located> {
+            let number = number.clone();
+            self.request(|reply| Command::FindByNumber { number, reply })
+                .await
+                .ok()
+                .flatten()
+        }
+
+        async fn link(&mut self, peer: ProcessorHandle) -> Option<ProcessorHandle> {
+            self.request(|reply| Command::Link { peer, reply })
+                .await
+                .ok()
+                .flatten()
+        }
+
+        async fn unlink(&mut self, peer_id: Uuid) -> Option<ProcessorHandle> {
+            self.request(|reply| Command::Unlink { peer_id, reply })
+                .await
+                .ok()
+                .flatten()
+        }
+
+        async fn forward_declaration(
+            &mut self,
+            decl: &Declaration<Pending>,
+            visited: &[Uuid],
+        ) -> Result<Dispatch, Box<dyn Error>> {
+            let (decl, visited) = (decl.clone(), visited.to_vec());
+            self.try_request(|reply| Command::ForwardDeclaration {
+                decl,
+                visited,
+                reply,
+            })
+            .await
+        }
+
+        async fn find_in_federation(&self, id: Uuid, visited: &[Uuid]) -> Option<Located> {
+            let visited = visited.to_vec();
+            self.request(|reply| Command::FindInFederation { id, visited, reply })
+                .await
+                .ok()
+                .flatten()
+        }
+
+        async fn remove_declaration(&mut self, id: Uuid) -> Option<DeclarationGeneric> {
+            self.request(|reply| Command::RemoveDeclaration { id, reply })
+                .await
+                .ok()
+                .flatten()
+        }
+
+        async fn customs_load(&self) -> HashMap<Uuid, f64> {
+            self.request(|reply| Command::CustomsLoad { reply })
+                .await
+                .unwrap_or_default()
+        }
+
+        async fn dispatch_history(&self, declaration_id: Uuid) -> Vec<DispatchRecord> {
+            self.request(|reply| Command::DispatchHistory {
+                declaration_id,
+                reply,
+            })
+            .await
+            .unwrap_or_default()
+        }
+
+        async fn drain(&mut self, id: Uuid) -> Result<Vec<Relocation>, Box<dyn Error>> {
+            self.try_request(|reply| Command::Drain { id, reply }).await
+        }
+
+        async fn redispatch_stale(&mut self, at: DateTime<Utc>) -> Vec<Relocation> {
+            self.request(|reply| Command::RedispatchStale { at, reply })
+                .await
+                .unwrap_or_default()
+        }
+
+        async fn resume(&mut self, id: Uuid) -> Result<(), Box<dyn Error>> {
+            self.try_request(|reply| Command::Resume { id, reply })
+                .await
+        }
+
+        async fn disconnect(
+            &mut self,
+            id: Uuid,
+        ) -> Result<(Customs, Vec<Relocation>), Box<dyn Error>> {
+            self.try_request(|reply| Command::Disconnect { id, reply })
+                .await
+        }
+
+        /// Decision is made before owner gets it, so declaration stays decided,
+        /// even if owner refuses it
+        async fn approve<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Approved>, Box<dyn Error>> {
+            let (held, reason) = (held_by(owner, id).await, reason.to_string());
+            let approved = self
+                .try_request(|reply| Command::Approve {
+                    id,
+                    held,
+                    reason,
+                    reply,
+                })
+                .await?;
+            owner
+                .update_declaration(&DeclarationGeneric::Approved(approved.clone()))
+                .await?;
+
+            Ok(approved)
+        }
+
+        /// Same as `approve`, but declaration is rejected
+        async fn reject<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Rejected>, Box<dyn Error>> {
+            let (held, reason) = (held_by(owner, id).await, reason.to_string());
+            let rejected = self
+                .try_request(|reply| Command::Reject {
+                    id,
+                    held,
+                    reason,
+                    reply,
+                })
+                .await?;
+            owner
+                .update_declaration(&DeclarationGeneric::Rejected(rejected.clone()))
+                .await?;
+
+            Ok(rejected)
+        }
+
+        /// Same as `approve`, declaration waits for amendment, even if owner refuses it
+        async fn request_info<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            fields: &[&str],
+            message: &str,
+        ) -> Result<Declaration<InfoRequested>, Box<dyn Error>> {
+            let held = held_by(owner, id).await;
+            let fields = fields.iter().map(|field| field.to_string()).collect();
+            let message = message.to_string();
+            let requested = self
+                .try_request(|reply| Command::RequestInfo {
+                    id,
+                    held,
+                    fields,
+                    message,
+                    reply,
+                })
+                .await?;
+            owner
+                .update_declaration(&DeclarationGeneric::InfoRequested(requested.clone()))
+                .await?;
+
+            Ok(requested)
+        }
+
+        async fn resubmit(
+            &mut self,
+            declaration: Declaration<InfoRequested>,
+        ) -> Result<Declaration<Inspecting>, Box<dyn Error>> {
+            let declaration = Box::new(declaration);
+            self.try_request(|reply| Command::Resubmit { declaration, reply })
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::logic::*;
+    use super::*;
+    use crate::models::{
+        customs::inspector::Inspector,
+        declaration::GenericDowncast,
+        misc::location::Location,
+        participants::declarant::{logic::Participant, Declarant},
+    };
+
+    #[tokio::test]
+    async fn concurrent_handles() {
+        let mut handle = ProcessorHandle::spawn(Processor::new().await).await;
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        handle.connect(customs.clone()).await.unwrap();
+
+        let (mut first, mut second) = (handle.clone(), handle.clone());
+        let (first_decl, second_decl): (Declaration<Pending>, Declaration<Pending>) = (
+            Declaration::new().await.into(),
+            Declaration::new().await.into(),
+        );
+        let (first_dispatch, second_dispatch) = tokio::join!(
+            first.process_declaration(&first_decl),
+            second.process_declaration(&second_decl)
+        );
+        assert_eq!(
+            first_dispatch.unwrap(),
+            Dispatch::Immediate(customs.id().await)
+        );
+        assert!(second_dispatch.is_ok());
+
+        let found = handle
+            .find_declaration(second_decl.id().await)
+            .await
+            .unwrap();
+        let found: &Declaration<Pending> = found.declaration.downcast().unwrap();
+        assert_eq!(found.id().await, second_decl.id().await);
+        assert_eq!(
+            handle.dispatch_history(first_decl.id().await).await.len(),
+            1
+        );
+
+        // Errors of Processor keep their type on the way back
+        let err = handle.drain(Uuid::new_v4()).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<crate::errors::processor::Err>(),
+            Some(crate::errors::processor::Err::CustomsNotFound(_))
+        ));
+
+        let processor = handle.shutdown().await.unwrap();
+        assert_eq!(
+            processor.customs_ref().await[customs.id_ref().await]
+                .declarations_ref()
+                .await
+                .len(),
+            2
+        );
+        let err = first.connect(customs).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ChErr>(),
+            Some(ChErr::ChannelWasClosed)
+        ));
+        assert!(first.enqueue(first_decl).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn participant_through_handle() {
+        let handle = ProcessorHandle::spawn(Processor::new().await).await;
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        handle.clone().connect(customs).await.unwrap();
+
+        let mut declarant = Declarant::new("John").await;
+        let mut decl = Declaration::new().await;
+        decl.set_product_name("Test")
+            .await
+            .set_product_code("0101210000")
+            .await
+            .set_product_price(1.0)
+            .await
+            .set_product_quantity(1)
+            .await
+            .set_product_weight(1.0)
+            .await
+            .set_product_description("Test")
+            .await
+            .set_transport_type("Test")
+            .await
+            .set_transport_name("Test")
+            .await
+            .set_sender_name("Test")
+            .await
+            .set_receiver_name("Test")
+            .await
+            .set_destination("Test")
+            .await
+            .set_departure("Test departure")
+            .await;
+        let id = decl.id().await;
+        declarant
+            .update_declaration(&DeclarationGeneric::Draft(decl))
+            .await
+            .unwrap();
+
+        declarant.send_docs(&mut handle.clone(), id).await.unwrap();
+        assert!(handle.find_declaration(id).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn decisions_through_handle() {
+        let mut handle = ProcessorHandle::spawn(Processor::new().await).await;
+        let inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let inspector_id = inspector.id().await;
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        customs
+            .inspectors_mut()
+            .await
+            .insert(inspector_id, inspector);
+        let customs_id = customs.id().await;
+        handle.connect(customs).await.unwrap();
+
+        let mut declarant = Declarant::new("John").await;
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let decl: Declaration<Pending> = Declaration::new().await.into();
+            ids.push(decl.id().await);
+            declarant
+                .update_declaration(&DeclarationGeneric::Pending(decl.clone()))
+                .await
+                .unwrap();
+            handle.process_declaration(&decl).await.unwrap();
+            handle.assign_next(customs_id, inspector_id).await.unwrap();
+        }
+
+        // Participant, who doesn't own declaration, can't get decision on it
+        let mut stranger = Declarant::new("Jane").await;
+        assert!(handle
+            .approve(&mut stranger, ids[0], "Documents are correct")
+            .await
+            .is_err());
+        handle
+            .approve(&mut declarant, ids[0], "Documents are correct")
+            .await
+            .unwrap();
+        assert!(matches!(
+            declarant.get_declaration(ids[0]).await,
+            Some(DeclarationGeneric::Approved(_))
+        ));
+        assert!(handle.find_declaration(ids[0]).await.is_none());
+
+        handle
+            .request_info(&mut declarant, ids[1], &["sender_name"], "Who sends it?")
+            .await
+            .unwrap();
+        declarant
+            .amend_declaration(ids[1], "sender_name", "Jane")
+            .await
+            .unwrap();
+        declarant.resubmit(&mut handle, ids[1]).await.unwrap();
+        handle
+            .reject(&mut declarant, ids[1], "Goods are banned")
+            .await
+            .unwrap();
+        assert!(matches!(
+            declarant.get_declaration(ids[1]).await,
+            Some(DeclarationGeneric::Rejected(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sla_background_check() {
+        let mut processor = Processor::new().await;
+        // Every declaration is stale at once, but there is no other customs to move it to
+        processor.set_sla(Some(chrono::Duration::zero())).await;
+        let handle = ProcessorHandle::spawn(processor).await;
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        handle.clone().connect(customs.clone()).await.unwrap();
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        handle.clone().process_declaration(&decl).await.unwrap();
+
+        // Clock is paused, so it only moves, when every task waits for it
+        tokio::time::sleep(SLA_CHECK_PERIOD + std::time::Duration::from_secs(1)).await;
+        let history = handle.dispatch_history(decl.id().await).await;
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history[1].reason_ref().await,
+            &crate::models::processor::record::Reason::SlaExpired(customs.id().await)
+        );
+        assert_eq!(history[1].dispatch().await, None);
+        let found = handle.find_declaration(decl.id().await).await.unwrap();
+        assert_eq!(found.customs_id, customs.id().await);
+    }
+
+    #[tokio::test]
+    async fn federation() {
+        let mut moscow = ProcessorHandle::spawn(Processor::new().await).await;
+        let mut kazan = ProcessorHandle::spawn(Processor::new().await).await;
+        let mut customs = Customs::new("Kazan", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        kazan.connect(customs.clone()).await.unwrap();
+        assert!(moscow.link(moscow.clone()).await.is_none());
+        moscow.link(kazan.clone()).await;
+        kazan.link(moscow.clone()).await;
+
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        let dispatch = moscow.process_declaration(&decl).await.unwrap();
+        assert_eq!(
+            dispatch,
+            Dispatch::Forwarded {
+                processor_id: kazan.id().await,
+                customs_id: Some(customs.id().await),
+            }
+        );
+        // Record of the forwarding processor tells where declaration went
+        let history = moscow.dispatch_history(decl.id().await).await;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].dispatch().await, Some(dispatch));
+        assert!(history[0].error_ref().await.is_none());
+        let found = moscow.find_declaration(decl.id().await).await.unwrap();
+        assert_eq!(found.processor_id, kazan.id().await);
+        assert_eq!(found.customs_id, customs.id().await);
+        let history = kazan.dispatch_history(decl.id().await).await;
+        assert_eq!(
+            history[0].reason_ref().await,
+            &crate::models::processor::record::Reason::Forwarded(moscow.id().await)
+        );
+
+        // Nobody is competent, so declaration must not bounce between processors
+        kazan.drain(customs.id().await).await.unwrap();
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        assert!(moscow.process_declaration(&decl).await.is_err());
+        assert!(moscow.find_declaration(decl.id().await).await.is_none());
+
+        moscow.unlink(kazan.id().await).await;
+        assert!(moscow.process_declaration(&decl).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn federation_without_deadlock() {
+        let mut moscow = ProcessorHandle::spawn(Processor::new().await).await;
+        let mut kazan = ProcessorHandle::spawn(Processor::new().await).await;
+        moscow.link(kazan.clone()).await;
+        kazan.link(moscow.clone()).await;
+
+        // Both forward to each other at once, neither waits for the other's command loop
+        let (first, second): (Declaration<Pending>, Declaration<Pending>) = (
+            Declaration::new().await.into(),
+            Declaration::new().await.into(),
+        );
+        let (mut to_kazan, mut to_moscow) = (moscow.clone(), kazan.clone());
+        let (first, second) = tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            tokio::join!(
+                to_kazan.process_declaration(&first),
+                to_moscow.process_declaration(&second)
+            )
+        })
+        .await
+        .unwrap();
+        for res in [first, second] {
+            assert!(matches!(
+                res.unwrap_err()
+                    .downcast_ref::<crate::errors::processor::Err>(),
+                Some(crate::errors::processor::Err::NotForwarded(_))
+            ));
+        }
+
+        // Peers don't keep each other alive
+        let (moscow_weak, kazan_weak) = (moscow.downgrade().await, kazan.downgrade().await);
+        drop((moscow, kazan, to_kazan, to_moscow));
+        assert!(moscow_weak.upgrade().await.is_none());
+        assert!(kazan_weak.upgrade().await.is_none());
+    }
+}
@@ -0,0 +1,1660 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::prelude::*;
+
+use self::actor::{ProcessorHandle, WeakProcessorHandle};
+use self::record::DispatchRecord;
+use self::strategy::{DispatchStrategy, Random};
+use super::{
+    customs::Customs,
+    declaration::{Declaration, DeclarationGeneric, Pending},
+    misc::{queue::DeclarationQueue, registration::RegistrationNumber},
+};
+
+pub mod actor;
+pub mod record;
+pub mod strategy;
+
+/// How long Processor waits for peer's answer, so peer, that got stuck,
+/// doesn't keep declaration from the other peers
+const PEER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// How many dispatch records Processor keeps, older ones are dropped first
+const DISPATCH_LOG_LIMIT: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct Processor {
+    id: Uuid,
+    customs: HashMap<Uuid, Customs>,
+    /// Processors of other regions, declarations no customs here can take are forwarded to them.
+    /// Handles are weak, so linked processors don't keep each other alive
+    peers: HashMap<Uuid, WeakProcessorHandle>,
+    strategy: Arc<dyn DispatchStrategy>,
+    /// The latest dispatch records, see `DISPATCH_LOG_LIMIT`
+    dispatch_log: VecDeque<DispatchRecord>,
+    /// Customs, that don't accept new declarations
+    draining: HashSet<Uuid>,
+    /// Declarations waiting to be dispatched, the most urgent first.
+    /// Declarations, that every competent customs is too full to take, wait here as well
+    intake: DeclarationQueue,
+    queued: HashMap<Uuid, Declaration<Pending>>,
+    /// Which customs holds the declaration, declaration id -> customs id
+    index: HashMap<Uuid, Uuid>,
+    /// How long declaration may stay pending at customs, before it's moved elsewhere
+    sla: Option<Duration>,
+}
+
+impl Processor {
+    pub async fn new() -> Processor {
+        Self::with_strategy(Random).await
+    }
+
+    /// Create Processor, that picks customs using provided strategy
+    pub async fn with_strategy(strategy: impl DispatchStrategy + 'static) -> Processor {
+        Processor {
+            strategy: Arc::new(strategy),
+            ..Default::default()
+        }
+    }
+}
+
+/// The only place fields are initialized, so constructors can't drift apart
+impl Default for Processor {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            customs: HashMap::default(),
+            peers: HashMap::default(),
+            strategy: Arc::new(Random),
+            dispatch_log: VecDeque::default(),
+            draining: HashSet::default(),
+            intake: DeclarationQueue::default(),
+            queued: HashMap::default(),
+            index: HashMap::default(),
+            sla: None,
+        }
+    }
+}
+
+/// Routing decision made by Processor for a single declaration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dispatch {
+    /// Customs is open and may start processing right away
+    Immediate(Uuid),
+    /// All competent customs are closed, declaration waits for the one that opens soonest
+    Deferred {
+        customs_id: Uuid,
+        opens_at: DateTime<Utc>,
+    },
+    /// Every competent customs is at capacity, declaration waits in Processor's queue
+    /// with `position` declarations ahead of it
+    Queued { position: usize },
+    /// None of the customs could take declaration, so it went to peer Processor.
+    /// Customs id is None, if declaration is queued there
+    Forwarded {
+        processor_id: Uuid,
+        customs_id: Option<Uuid>,
+    },
+}
+
+impl Dispatch {
+    /// None, if declaration is still in Processor's queue
+    pub const fn customs_id(&self) -> Option<Uuid> {
+        match self {
+            Self::Immediate(customs_id) | Self::Deferred { customs_id, .. } => Some(*customs_id),
+            Self::Forwarded { customs_id, .. } => *customs_id,
+            Self::Queued { .. } => None,
+        }
+    }
+}
+
+/// Declaration moved away from drained customs, or the one it was pending at for too long
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    pub declaration_id: Uuid,
+    pub from: Uuid,
+    /// None, if no other customs could take the declaration, so it stayed where it was
+    pub to: Option<Dispatch>,
+}
+
+/// Outcome of dispatching declaration to connected customs, see `Processor::submit_locally`
+#[derive(Debug)]
+pub(super) enum Submitted {
+    /// Declaration was dispatched here, or can't be dispatched anywhere
+    Done(Result<Dispatch, Box<dyn Error + Send + Sync>>),
+    /// None of the customs could take declaration, so it goes to peers
+    Forward {
+        /// Record, that is updated, once a peer takes declaration
+        record_id: Uuid,
+        peers: Vec<ProcessorHandle>,
+        visited: Vec<Uuid>,
+    },
+}
+
+/// Declaration found by Processor and where it is held
+#[derive(Debug, Clone, PartialEq)]
+pub struct Located {
+    pub declaration: DeclarationGeneric,
+    pub processor_id: Uuid,
+    pub customs_id: Uuid,
+    /// None, if declaration is still pending
+    pub inspector_id: Option<Uuid>,
+}
+
+///
+/// We Hide Business Logic behind seperate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs and cut out any logic.
+///
+pub mod logic {
+    use uuid::Uuid;
+
+    use crate::models::customs::{
+        inspector::{logic::Logic as InspectorLogic, Inspector},
+        logic::Logic as CustomsLogic,
+        Customs,
+    };
+
+    use super::record::{Candidate as Considered, Reason, Verdict};
+    use super::strategy::Candidate;
+    use super::*;
+    use crate::errors::{declaration::Err as DErr, into_send, processor::Err as PErr};
+    use crate::models::declaration::{Approved, InfoRequested, Inspecting, Rejected};
+    use crate::models::participants::Participant;
+    use std::error::Error;
+    pub trait Logic {
+        async fn connect(&mut self, customs: Customs) -> Result<Option<Customs>, Box<dyn Error>>;
+        /// Send declaration to customs and report where it went.
+        /// If every competent customs is full, declaration is queued instead
+        async fn process_declaration(
+            &mut self,
+            decl: &Declaration<Pending>,
+        ) -> Result<Dispatch, Box<dyn Error>>;
+        /// Look declaration up by id, together with customs and inspector holding it.
+        /// Peers are searched as well
+        async fn find_declaration(&self, id: Uuid) -> Option<Located>;
+        /// Look declaration up by registration number among connected customs
+        async fn find_by_number(&self, number: &RegistrationNumber) -> Option<Located>;
+        /// Link peer Processor, returns the previously linked one with the same id.
+        /// Peer is held weakly, it's gone, once every other handle to it is dropped
+        async fn link(&mut self, peer: ProcessorHandle) -> Option<ProcessorHandle>;
+        async fn unlink(&mut self, peer_id: Uuid) -> Option<ProcessorHandle>;
+        /// Process declaration forwarded by peers. Processors in `visited` have already
+        /// tried it, so declaration is never forwarded back to them
+        async fn forward_declaration(
+            &mut self,
+            decl: &Declaration<Pending>,
+            visited: &[Uuid],
+        ) -> Result<Dispatch, Box<dyn Error>>;
+        /// Look declaration up here and at peers, except the `visited` ones
+        async fn find_in_federation(&self, id: Uuid, visited: &[Uuid]) -> Option<Located>;
+        /// Take declaration out of customs, that holds it
+        async fn remove_declaration(&mut self, id: Uuid) -> Option<DeclarationGeneric>;
+        /// Put declaration into intake queue, returns number of declarations ahead of it
+        async fn enqueue(&mut self, decl: Declaration<Pending>) -> Result<usize, Box<dyn Error>>;
+        /// Dispatch queued declarations, the most urgent first. Declarations, that
+        /// couldn't be dispatched, stay in the queue and are reported with None.
+        /// Called on its own, whenever connected customs may have freed up capacity
+        async fn dispatch_queued(&mut self) -> Vec<(Uuid, Option<Dispatch>)>;
+        /// Hand the most urgent pending declaration of customs over to its inspector
+        async fn assign_next(
+            &mut self,
+            customs_id: Uuid,
+            inspector_id: Uuid,
+        ) -> Result<Option<Uuid>, Box<dyn Error>>;
+        /// Pending declarations per inspector for every connected customs
+        async fn customs_load(&self) -> HashMap<Uuid, f64>;
+        /// Dispatch records of the declaration, oldest first
+        async fn dispatch_history(&self, declaration_id: Uuid) -> Vec<DispatchRecord>;
+        /// Stop routing to customs and move its pending declarations to other customs
+        async fn drain(&mut self, id: Uuid) -> Result<Vec<Relocation>, Box<dyn Error>>;
+        /// Move declarations, that are pending longer than SLA, to other customs, that are open.
+        /// Does nothing, if Processor has no SLA set
+        async fn redispatch_stale(&mut self, at: DateTime<Utc>) -> Vec<Relocation>;
+        /// Let drained customs receive declarations again
+        async fn resume(&mut self, id: Uuid) -> Result<(), Box<dyn Error>>;
+        /// Drain customs and remove it from Processor. Declarations, that couldn't be moved,
+        /// are left inside the returned customs
+        async fn disconnect(
+            &mut self,
+            id: Uuid,
+        ) -> Result<(Customs, Vec<Relocation>), Box<dyn Error>>;
+        /// Approve declaration on behalf of inspector, who is inspecting it, and deliver
+        /// the decision to its owner. Decided declaration is no longer held by customs
+        async fn approve<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Approved>, Box<dyn Error>>;
+        /// Same as `approve`, but declaration is rejected
+        async fn reject<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Rejected>, Box<dyn Error>>;
+        /// Ask owner to amend the given fields on behalf of inspector, who is inspecting
+        /// declaration. Declaration stays at customs, until owner resubmits it
+        async fn request_info<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            fields: &[&str],
+            message: &str,
+        ) -> Result<Declaration<InfoRequested>, Box<dyn Error>>;
+        /// Take amended declaration back to inspector, who requested information on it
+        async fn resubmit(
+            &mut self,
+            declaration: Declaration<InfoRequested>,
+        ) -> Result<Declaration<Inspecting>, Box<dyn Error>>;
+    }
+
+    impl Logic for super::Processor {
+        #[tracing::instrument]
+        async fn connect(&mut self, customs: Customs) -> Result<Option<Customs>, Box<dyn Error>> {
+            let id = customs.id().await;
+            let declarations = customs.declaration_ids().await;
+            let customs = self.customs.insert(id, customs);
+            if customs.is_some() {
+                tracing::warn!("Customs with id {} already exists. Overwriting.", id);
+                self.index.retain(|_, customs_id| *customs_id != id);
+            }
+            for declaration_id in declarations {
+                self.index.insert(declaration_id, id);
+            }
+            self.release_queued().await;
+
+            Ok(customs)
+        }
+
+        /// Send declaration to customs.
+        #[tracing::instrument]
+        async fn process_declaration(
+            &mut self,
+            decl: &Declaration<Pending>,
+        ) -> Result<Dispatch, Box<dyn Error>> {
+            self.submit(decl, Reason::Submitted, &[]).await
+        }
+
+        #[tracing::instrument]
+        async fn find_declaration(&self, id: Uuid) -> Option<Located> {
+            tracing::info!("Requested declaration {}", id);
+            let found = self.find_in_federation(id, &[]).await;
+            if found.is_none() {
+                tracing::warn!("Declaration {} not found", id);
+            }
+
+            found
+        }
+
+        async fn find_by_number(&self, number: &RegistrationNumber) -> Option<Located> {
+            for customs in self.customs.values() {
+                if let Some(id) = customs.find_by_number(number).await {
+                    return self.find_locally(id).await;
+                }
+            }
+            tracing::warn!("Declaration {} not found", number);
+
+            None
+        }
+
+        async fn link(&mut self, peer: ProcessorHandle) -> Option<ProcessorHandle> {
+            let peer_id = peer.id().await;
+            if peer_id == self.id {
+                tracing::warn!("Processor {} can't be its own peer", peer_id);
+                return None;
+            }
+            tracing::info!("Processor {} linked to {}", self.id, peer_id);
+            let previous = self.peers.insert(peer_id, peer.downgrade().await)?;
+
+            previous.upgrade().await
+        }
+
+        async fn unlink(&mut self, peer_id: Uuid) -> Option<ProcessorHandle> {
+            self.peers.remove(&peer_id)?.upgrade().await
+        }
+
+        #[tracing::instrument]
+        async fn forward_declaration(
+            &mut self,
+            decl: &Declaration<Pending>,
+            visited: &[Uuid],
+        ) -> Result<Dispatch, Box<dyn Error>> {
+            let from = visited.last().copied().unwrap_or_default();
+            self.submit(decl, Reason::Forwarded(from), visited).await
+        }
+
+        async fn find_in_federation(&self, id: Uuid, visited: &[Uuid]) -> Option<Located> {
+            if self.index.contains_key(&id) {
+                return self.find_locally(id).await;
+            }
+            let (peers, visited) = self.peers_to_ask(visited).await;
+
+            Self::find_at_peers(id, peers, &visited).await
+        }
+
+        #[tracing::instrument]
+        async fn remove_declaration(&mut self, id: Uuid) -> Option<DeclarationGeneric> {
+            let customs_id = self.index.remove(&id)?;
+            let customs = self.customs.get_mut(&customs_id)?;
+            let mut removed = customs
+                .remove_declaration(&id)
+                .await
+                .map(DeclarationGeneric::Pending);
+            if removed.is_none() {
+                for inspector in customs.inspectors_mut().await.values_mut() {
+                    if let Some(decl) = inspector.remove_declaration(&id).await {
+                        removed = Some(DeclarationGeneric::Inspecting(decl));
+                        break;
+                    }
+                }
+            }
+            self.release_queued().await;
+
+            removed
+        }
+
+        async fn enqueue(&mut self, decl: Declaration<Pending>) -> Result<usize, Box<dyn Error>> {
+            Ok(self.queue(decl).await)
+        }
+
+        #[tracing::instrument]
+        async fn dispatch_queued(&mut self) -> Vec<(Uuid, Option<Dispatch>)> {
+            let ids = self.intake.ids().await;
+            let mut report = Vec::with_capacity(ids.len());
+            for id in ids {
+                let Some(decl) = self.queued.get(&id).cloned() else {
+                    self.intake.remove(&id).await;
+                    continue;
+                };
+                let (_, dispatch) = self.dispatch(&decl, Reason::Dequeued).await;
+                let dispatch = dispatch.map_err(|err| err.to_string());
+                match dispatch {
+                    Ok(dispatch) => {
+                        self.intake.remove(&id).await;
+                        self.queued.remove(&id);
+                        report.push((id, Some(dispatch)));
+                    }
+                    Err(err) => {
+                        tracing::warn!("Declaration {} stays in the queue: {}", id, err);
+                        report.push((id, None));
+                    }
+                }
+            }
+
+            report
+        }
+
+        async fn assign_next(
+            &mut self,
+            customs_id: Uuid,
+            inspector_id: Uuid,
+        ) -> Result<Option<Uuid>, Box<dyn Error>> {
+            let customs = self
+                .customs
+                .get_mut(&customs_id)
+                .ok_or(PErr::CustomsNotFound(customs_id))?;
+            let assigned = customs.assign_next(&inspector_id).await?;
+            if assigned.is_some() {
+                self.release_queued().await;
+            }
+
+            Ok(assigned)
+        }
+
+        async fn customs_load(&self) -> HashMap<Uuid, f64> {
+            let mut load = HashMap::with_capacity(self.customs.len());
+            for (id, customs) in &self.customs {
+                load.insert(*id, customs.load().await);
+            }
+
+            load
+        }
+
+        async fn dispatch_history(&self, declaration_id: Uuid) -> Vec<DispatchRecord> {
+            let mut history = Vec::new();
+            for record in &self.dispatch_log {
+                if record.declaration_id().await == declaration_id {
+                    history.push(record.clone());
+                }
+            }
+
+            history
+        }
+
+        #[tracing::instrument]
+        async fn drain(&mut self, id: Uuid) -> Result<Vec<Relocation>, Box<dyn Error>> {
+            if !self.customs.contains_key(&id) {
+                return Err(Box::new(PErr::CustomsNotFound(id)));
+            }
+            self.draining.insert(id);
+            let customs = &self.customs[&id];
+            let declarations = customs.declarations_ref().await;
+            let mut pending = Vec::with_capacity(declarations.len());
+            for declaration_id in customs.queue_ref().await.ids().await {
+                if let Some(decl) = declarations.get(&declaration_id) {
+                    pending.push(decl.clone());
+                }
+            }
+            tracing::info!(
+                "Draining customs {}, {} declarations to move",
+                id,
+                pending.len()
+            );
+
+            let mut report = Vec::with_capacity(pending.len());
+            for decl in pending {
+                report.push(self.relocate(&decl, id, Reason::Drained(id)).await);
+            }
+
+            Ok(report)
+        }
+
+        #[tracing::instrument]
+        async fn redispatch_stale(&mut self, at: DateTime<Utc>) -> Vec<Relocation> {
+            let Some(sla) = self.sla else {
+                return Vec::new();
+            };
+            let mut stale = Vec::new();
+            for (customs_id, customs) in &self.customs {
+                // Closed customs can't pick declarations up, drained ones are handled by drain
+                if self.draining.contains(customs_id) || !customs.is_open(at).await {
+                    continue;
+                }
+                let declarations = customs.declarations_ref().await;
+                for declaration_id in customs.queue_ref().await.ids().await {
+                    if let Some(decl) = declarations.get(&declaration_id) {
+                        if decl.state_entered_at().await + sla <= at {
+                            stale.push((*customs_id, decl.clone()));
+                        }
+                    }
+                }
+            }
+            if !stale.is_empty() {
+                tracing::info!("{} declarations exceeded SLA of {}", stale.len(), sla);
+            }
+
+            let mut report = Vec::with_capacity(stale.len());
+            for (from, decl) in stale {
+                report.push(self.relocate(&decl, from, Reason::SlaExpired(from)).await);
+            }
+
+            report
+        }
+
+        async fn resume(&mut self, id: Uuid) -> Result<(), Box<dyn Error>> {
+            if !self.customs.contains_key(&id) {
+                return Err(Box::new(PErr::CustomsNotFound(id)));
+            }
+            if self.draining.remove(&id) {
+                tracing::info!("Customs {} accepts declarations again", id);
+                self.release_queued().await;
+            }
+
+            Ok(())
+        }
+
+        async fn disconnect(
+            &mut self,
+            id: Uuid,
+        ) -> Result<(Customs, Vec<Relocation>), Box<dyn Error>> {
+            let report = self.drain(id).await?;
+            self.draining.remove(&id);
+            let customs = self.customs.remove(&id).ok_or(PErr::CustomsNotFound(id))?;
+            self.index.retain(|_, customs_id| *customs_id != id);
+            tracing::info!("Customs {} disconnected", id);
+
+            Ok((customs, report))
+        }
+
+        async fn approve<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Approved>, Box<dyn Error>> {
+            let (customs_id, inspector) = self.inspector_of(id).await?;
+            let approved = inspector.approve(owner, &id, reason).await?;
+            self.forget(id, customs_id).await;
+
+            Ok(approved)
+        }
+
+        async fn reject<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            reason: &str,
+        ) -> Result<Declaration<Rejected>, Box<dyn Error>> {
+            let (customs_id, inspector) = self.inspector_of(id).await?;
+            let rejected = inspector.reject(owner, &id, reason).await?;
+            self.forget(id, customs_id).await;
+
+            Ok(rejected)
+        }
+
+        async fn request_info<P: Participant>(
+            &mut self,
+            owner: &mut P,
+            id: Uuid,
+            fields: &[&str],
+            message: &str,
+        ) -> Result<Declaration<InfoRequested>, Box<dyn Error>> {
+            let (_, inspector) = self.inspector_of(id).await?;
+
+            inspector.request_info(owner, &id, fields, message).await
+        }
+
+        async fn resubmit(
+            &mut self,
+            declaration: Declaration<InfoRequested>,
+        ) -> Result<Declaration<Inspecting>, Box<dyn Error>> {
+            let (_, inspector) = self.inspector_of(declaration.id().await).await?;
+
+            inspector.resubmit(declaration).await
+        }
+    }
+
+    /// Steps of `submit` and `find_in_federation`, that actor takes apart,
+    /// so waiting for peers doesn't hold up its command loop
+    impl super::Processor {
+        /// Dispatch declaration to connected customs, or tell which peers to forward it to
+        pub(super) async fn submit_locally(
+            &mut self,
+            decl: &Declaration<Pending>,
+            reason: Reason,
+            visited: &[Uuid],
+        ) -> Submitted {
+            let (record_id, dispatch) = self.dispatch(decl, reason).await;
+            match dispatch.map_err(into_send) {
+                Ok(dispatch) => Submitted::Done(Ok(dispatch)),
+                Err(err) if !Self::is_forwardable(err.as_ref()) => Submitted::Done(Err(err)),
+                Err(err) => {
+                    tracing::info!("Declaration {} goes to peers: {}", decl.id().await, err);
+                    let (peers, visited) = self.peers_to_ask(visited).await;
+                    Submitted::Forward {
+                        record_id,
+                        peers,
+                        visited,
+                    }
+                }
+            }
+        }
+
+        /// Forward declaration to the first peer, that takes it
+        pub(super) async fn forward_to_peers(
+            decl: &Declaration<Pending>,
+            peers: Vec<ProcessorHandle>,
+            visited: &[Uuid],
+        ) -> Result<Dispatch, Box<dyn Error + Send + Sync>> {
+            for mut peer in peers {
+                let peer_id = peer.id().await;
+                let forwarded =
+                    tokio::time::timeout(PEER_TIMEOUT, peer.forward_declaration(decl, visited))
+                        .await
+                        .map_err(|_| "peer didn't answer in time".to_string())
+                        .and_then(|res| res.map_err(|err| err.to_string()));
+                let dispatch = match forwarded {
+                    Ok(dispatch @ Dispatch::Forwarded { .. }) => dispatch,
+                    Ok(dispatch) => Dispatch::Forwarded {
+                        processor_id: peer_id,
+                        customs_id: dispatch.customs_id(),
+                    },
+                    Err(err) => {
+                        tracing::warn!("Peer {} didn't take declaration: {}", peer_id, err);
+                        continue;
+                    }
+                };
+                tracing::info!(
+                    "Declaration {} forwarded to peer {}",
+                    decl.id().await,
+                    peer_id
+                );
+
+                return Ok(dispatch);
+            }
+
+            Err(Box::new(PErr::NotForwarded(decl.id().await)))
+        }
+
+        /// Record of the forwarded declaration tells where it went
+        pub(super) async fn note_forwarded(&mut self, record_id: Uuid, dispatch: Dispatch) {
+            if let Some(record) = self.record_mut(record_id).await {
+                record
+                    .set_dispatch(Some(dispatch))
+                    .await
+                    .set_error(None)
+                    .await;
+            }
+        }
+
+        /// Declaration held by connected customs
+        pub(super) async fn find_locally(&self, id: Uuid) -> Option<Located> {
+            let customs_id = self.index.get(&id)?;
+            let customs = self.customs.get(customs_id)?;
+
+            Some(Located {
+                declaration: customs.get_declaration(&id).await?,
+                processor_id: self.id,
+                customs_id: *customs_id,
+                inspector_id: customs.inspector_of(&id).await,
+            })
+        }
+
+        /// Peers to ask about declaration along with processors, that have already been asked
+        pub(super) async fn peers_to_ask(
+            &self,
+            visited: &[Uuid],
+        ) -> (Vec<ProcessorHandle>, Vec<Uuid>) {
+            let visited = self.visited(visited).await;
+
+            (self.peers_except(&visited).await, visited)
+        }
+
+        /// Ask peers one by one, until one of them finds declaration
+        pub(super) async fn find_at_peers(
+            id: Uuid,
+            peers: Vec<ProcessorHandle>,
+            visited: &[Uuid],
+        ) -> Option<Located> {
+            for peer in peers {
+                let found =
+                    tokio::time::timeout(PEER_TIMEOUT, peer.find_in_federation(id, visited)).await;
+                if let Ok(Some(found)) = found {
+                    return Some(found);
+                }
+            }
+
+            None
+        }
+    }
+
+    /// Private methods
+    impl super::Processor {
+        /// Customs holding declaration and inspector, who is inspecting it
+        /// or waits for its amendment
+        async fn inspector_of(&mut self, id: Uuid) -> Result<(Uuid, &mut Inspector), DErr> {
+            let customs_id = *self.index.get(&id).ok_or(DErr::DeclarationNotFound(id))?;
+            let customs = self
+                .customs
+                .get_mut(&customs_id)
+                .ok_or(DErr::DeclarationNotFound(id))?;
+            let inspector_id = customs
+                .inspector_of(&id)
+                .await
+                .ok_or(DErr::DeclarationNotFound(id))?;
+            let inspector = customs
+                .inspectors_mut()
+                .await
+                .get_mut(&inspector_id)
+                .ok_or(DErr::DeclarationNotFound(id))?;
+
+            Ok((customs_id, inspector))
+        }
+
+        /// Declaration is decided, so neither customs nor index keep it anymore
+        async fn forget(&mut self, id: Uuid, customs_id: Uuid) {
+            self.index.remove(&id);
+            if let Some(customs) = self.customs.get_mut(&customs_id) {
+                customs.remove_declaration(&id).await;
+            }
+            self.release_queued().await;
+        }
+
+        /// Pick customs, log the decision and hand declaration over to customs.
+        /// Returns id of the logged record along with the result.
+        /// Holds no `Box<dyn Error>` across awaits, so Processor can run as an actor
+        async fn dispatch(
+            &mut self,
+            decl: &Declaration<Pending>,
+            reason: Reason,
+        ) -> (Uuid, Result<Dispatch, Box<dyn Error>>) {
+            let at = Utc::now();
+            let may_queue = matches!(reason, Reason::Submitted | Reason::Forwarded(_));
+            let exclude = match reason {
+                Reason::SlaExpired(customs_id) => Some(customs_id),
+                _ => None,
+            };
+            let mut record = DispatchRecord::new(decl.id().await, reason, at).await;
+            let mut dispatch = self.pick_customs(decl, at, exclude, &mut record).await;
+            if may_queue && matches!(dispatch, Err(PErr::CustomsAtCapacity(_))) {
+                let position = self.queue(decl.clone()).await;
+                dispatch = Ok(Dispatch::Queued { position });
+            }
+            // Closed customs would make stale declaration wait even longer
+            if exclude.is_some() && matches!(dispatch, Ok(Dispatch::Deferred { .. })) {
+                dispatch = Err(PErr::NoOpenCustoms(decl.id().await));
+            }
+            // Record is kept only once customs took declaration or refused it
+            let dispatch = match dispatch {
+                Ok(dispatch) => self.hand_over(decl, dispatch).await.map_err(into_send),
+                Err(err) => Err(into_send(Box::new(err))),
+            };
+            match &dispatch {
+                Ok(dispatch) => record.set_dispatch(Some(*dispatch)).await,
+                Err(err) => record.set_error(Some(err.to_string())).await,
+            };
+            tracing::info!("Dispatch record: {:?}", record);
+            let record_id = record.id().await;
+            self.log(record).await;
+
+            (record_id, dispatch.map_err(|err| -> Box<dyn Error> { err }))
+        }
+
+        /// Send declaration to customs picked for it, queued and forwarded ones stay where they are
+        async fn hand_over(
+            &mut self,
+            decl: &Declaration<Pending>,
+            dispatch: Dispatch,
+        ) -> Result<Dispatch, Box<dyn Error>> {
+            let index = match dispatch {
+                Dispatch::Immediate(index) => {
+                    tracing::info!(
+                        "Sending declaration {} to customs {}",
+                        decl.id().await,
+                        index
+                    );
+                    index
+                }
+                Dispatch::Deferred {
+                    customs_id,
+                    opens_at,
+                } => {
+                    tracing::info!(
+                        "Sending declaration {} to customs {}, closed until {}",
+                        decl.id().await,
+                        customs_id,
+                        opens_at
+                    );
+                    customs_id
+                }
+                Dispatch::Queued { position } => {
+                    tracing::info!(
+                        "Every competent customs is full, declaration {} is queued at {}",
+                        decl.id().await,
+                        position
+                    );
+                    return Ok(dispatch);
+                }
+                // Customs never forward declarations themselves
+                Dispatch::Forwarded { .. } => return Ok(dispatch),
+            };
+            let customs = self
+                .customs
+                .get_mut(&index)
+                .ok_or(Box::new(PErr::CannotBorrowCustoms(index)))?;
+            customs.update_decl(decl.clone()).await?;
+            self.index.insert(decl.id().await, index);
+
+            Ok(dispatch)
+        }
+
+        /// Dispatch declaration to connected customs. If none of them is suitable,
+        /// forward declaration to peers, that aren't `visited` yet
+        async fn submit(
+            &mut self,
+            decl: &Declaration<Pending>,
+            reason: Reason,
+            visited: &[Uuid],
+        ) -> Result<Dispatch, Box<dyn Error>> {
+            let submitted = match self.submit_locally(decl, reason, visited).await {
+                Submitted::Done(res) => res,
+                Submitted::Forward {
+                    record_id,
+                    peers,
+                    visited,
+                } => {
+                    let forwarded = Self::forward_to_peers(decl, peers, &visited).await;
+                    if let Ok(dispatch) = forwarded {
+                        self.note_forwarded(record_id, dispatch).await;
+                    }
+                    forwarded
+                }
+            };
+
+            submitted.map_err(|err| -> Box<dyn Error> { err })
+        }
+
+        /// Errors, that another Processor might not run into
+        fn is_forwardable(err: &(dyn Error + 'static)) -> bool {
+            matches!(
+                err.downcast_ref::<PErr>(),
+                Some(PErr::NoCompetentCustoms(_) | PErr::NoSuitableCustoms(_))
+            )
+        }
+
+        async fn visited(&self, visited: &[Uuid]) -> Vec<Uuid> {
+            let mut visited = visited.to_vec();
+            visited.push(self.id);
+
+            visited
+        }
+
+        /// Peers, that are still alive, ordered by id, so they are asked in the same order every time
+        async fn peers_except(&self, visited: &[Uuid]) -> Vec<ProcessorHandle> {
+            let mut ids: Vec<&Uuid> = self
+                .peers
+                .keys()
+                .filter(|id| !visited.contains(id))
+                .collect();
+            ids.sort_unstable();
+            let mut peers = Vec::with_capacity(ids.len());
+            for id in ids {
+                if let Some(peer) = self.peers[id].upgrade().await {
+                    peers.push(peer);
+                }
+            }
+
+            peers
+        }
+
+        /// Dispatch declaration anew and take it out of the customs it was in,
+        /// if some other customs accepted it
+        async fn relocate(
+            &mut self,
+            decl: &Declaration<Pending>,
+            from: Uuid,
+            reason: Reason,
+        ) -> Relocation {
+            let declaration_id = decl.id().await;
+            let (_, to) = self.dispatch(decl, reason).await;
+            let to = to.map_err(|err| err.to_string());
+            match to {
+                Ok(dispatch) => {
+                    if let Some(customs) = self.customs.get_mut(&from) {
+                        customs.remove_declaration(&declaration_id).await;
+                    }
+                    Relocation {
+                        declaration_id,
+                        from,
+                        to: Some(dispatch),
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Declaration {} stays in customs {}: {}",
+                        declaration_id,
+                        from,
+                        err
+                    );
+                    Relocation {
+                        declaration_id,
+                        from,
+                        to: None,
+                    }
+                }
+            }
+        }
+
+        /// Keep dispatch record, dropping the oldest one, if log is full
+        async fn log(&mut self, record: DispatchRecord) {
+            if self.dispatch_log.len() >= DISPATCH_LOG_LIMIT {
+                self.dispatch_log.pop_front();
+            }
+            self.dispatch_log.push_back(record);
+        }
+
+        /// Logged record by id, recent records are looked up first
+        async fn record_mut(&mut self, id: Uuid) -> Option<&mut DispatchRecord> {
+            for record in self.dispatch_log.iter_mut().rev() {
+                if *record.id_ref().await == id {
+                    return Some(record);
+                }
+            }
+
+            None
+        }
+
+        /// Put declaration into intake queue, returns number of declarations ahead of it
+        async fn queue(&mut self, decl: Declaration<Pending>) -> usize {
+            let id = decl.id().await;
+            self.intake.push(id, decl.priority().await).await;
+            self.queued.insert(id, decl);
+
+            self.intake.position(&id).await.unwrap_or_default()
+        }
+
+        /// Try to place queued declarations, if there are any
+        async fn release_queued(&mut self) {
+            if !self.intake.is_empty().await {
+                self.dispatch_queued().await;
+            }
+        }
+
+        /// Pick customs among the competent ones, that are open at the given moment
+        /// and have room for declaration, using dispatch strategy. If all of them are closed,
+        /// pick the one that opens soonest. `exclude` is never picked, e.g. customs, that failed
+        /// to process declaration in time. Verdict on every customs is written into the record
+        async fn pick_customs(
+            &self,
+            decl: &Declaration<Pending>,
+            at: DateTime<Utc>,
+            exclude: Option<Uuid>,
+            record: &mut DispatchRecord,
+        ) -> Result<Dispatch, PErr> {
+            let mut competent = Vec::with_capacity(self.customs.len());
+            let mut full = false;
+            for (id, customs) in &self.customs {
+                if exclude == Some(*id) {
+                    record.candidates_mut().await.push(Considered {
+                        customs_id: *id,
+                        verdict: Verdict::Excluded,
+                    });
+                } else if self.draining.contains(id) {
+                    record.candidates_mut().await.push(Considered {
+                        customs_id: *id,
+                        verdict: Verdict::Draining,
+                    });
+                } else if !customs.is_competent(decl).await {
+                    record.candidates_mut().await.push(Considered {
+                        customs_id: *id,
+                        verdict: Verdict::Incompetent,
+                    });
+                } else if customs.is_full().await {
+                    full = true;
+                    record.candidates_mut().await.push(Considered {
+                        customs_id: *id,
+                        verdict: Verdict::Full,
+                    });
+                } else {
+                    competent.push((*id, customs.opens_in(at).await));
+                }
+            }
+            for (id, wait) in &competent {
+                if !wait.is_zero() {
+                    record.candidates_mut().await.push(Considered {
+                        customs_id: *id,
+                        verdict: Verdict::Closed {
+                            opens_at: at + *wait,
+                        },
+                    });
+                }
+            }
+            if competent.is_empty() && full {
+                return Err(PErr::CustomsAtCapacity(decl.id().await));
+            }
+            if competent.is_empty() {
+                tracing::warn!("No competent customs for declaration {}", decl.id().await);
+                return Err(PErr::NoCompetentCustoms(decl.id().await));
+            }
+
+            let mut open: Vec<Uuid> = competent
+                .iter()
+                .filter(|(_, wait)| wait.is_zero())
+                .map(|(id, _)| *id)
+                .collect();
+            if open.is_empty() {
+                let (customs_id, wait) = competent
+                    .into_iter()
+                    .min_by_key(|(_, wait)| *wait)
+                    .ok_or(PErr::NoCompetentCustoms(decl.id().await))?;
+                return Ok(Dispatch::Deferred {
+                    customs_id,
+                    opens_at: at + wait,
+                });
+            }
+
+            open.sort_unstable();
+            let mut candidates = Vec::with_capacity(open.len());
+            for id in &open {
+                candidates.push(Candidate::new(&self.customs[id]).await);
+            }
+            let scores = self.strategy.score(decl, &candidates).await;
+            for (id, score) in open.iter().zip(&scores) {
+                record.candidates_mut().await.push(Considered {
+                    customs_id: *id,
+                    verdict: score.map_or(Verdict::Rejected, Verdict::Scored),
+                });
+            }
+            let decl_id = decl.id().await;
+            open.into_iter()
+                .zip(scores)
+                .filter_map(|(id, score)| score.map(|score| (id, score)))
+                .fold(None, |best: Option<(Uuid, f64)>, (id, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((id, score)),
+                })
+                .map(|(id, _)| Dispatch::Immediate(id))
+                .ok_or_else(|| {
+                    tracing::warn!("Dispatch strategy rejected every open customs");
+                    PErr::NoSuitableCustoms(decl_id)
+                })
+        }
+    }
+}
+
+/// Boilerplate
+impl Processor {
+    setter!( { async } strategy: Arc<dyn DispatchStrategy>, { async } sla: Option<Duration>);
+    getter!( { async } sla: Option<Duration>);
+    getter_ref!( { async } strategy: &Arc<dyn DispatchStrategy>, { async } dispatch_log: &VecDeque<DispatchRecord>, { async } intake: &DeclarationQueue);
+    // Customs are changed only by connect and disconnect, which keep index up to date
+    getter_ref!( { async } customs: &HashMap<Uuid, Customs>, { async } peers: &HashMap<Uuid, WeakProcessorHandle>);
+    getter!( { async } id: Uuid);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::models::{
+        customs::{competence::Competence, inspector::Inspector, logic::Logic as CustomsLogic},
+        declaration::{Draft, GenericDowncast, Priority},
+        misc::{location::Location, registration::Registry},
+    };
+
+    use super::{
+        logic::*,
+        record::{Reason, Verdict},
+        strategy::{Candidate, LeastLoaded, RoundRobin, Scores},
+        *,
+    };
+    #[tokio::test]
+    async fn connect() {
+        let mut proc = Processor::new().await;
+        let location = Location::default();
+        let customs = Customs::new("Moscow", &location).await;
+        proc.connect(customs.clone()).await.unwrap();
+
+        assert_eq!(proc.customs.len(), 1);
+        assert_eq!(proc.customs[customs.id_ref().await], customs);
+    }
+
+    #[tokio::test]
+    async fn process_declaration() {
+        let mut proc = Processor::new().await;
+        let location = Location::default();
+        let customs = Customs::new("Moscow", &location).await;
+        proc.connect(customs.clone()).await.unwrap();
+        let mut decl = Declaration::<Draft>::default();
+        decl.set_receiver_name("TEST").await;
+
+        assert_eq!(proc.customs_ref().await.len(), 1);
+        assert_eq!(proc.customs[customs.id_ref().await], customs);
+    }
+
+    #[tokio::test]
+    async fn find_declaration() {
+        let mut proc = Processor::new().await;
+        let location = Location::default();
+        let mut customs = Customs::new("Moscow", &location).await;
+        customs
+            .set_registry(Some(Registry::new("10702020").await.unwrap()))
+            .await;
+        proc.connect(customs.clone()).await.unwrap();
+        let mut decl = Declaration::<Pending>::default();
+        decl.set_receiver_name("TEST").await;
+        proc.process_declaration(&decl).await.unwrap();
+        let found = proc.find_declaration(decl.id().await).await.unwrap();
+        let decl: &Declaration<Pending> = found.declaration.downcast().unwrap();
+
+        assert_eq!(decl.receiver_name_ref().await, "TEST");
+        assert_eq!(found.customs_id, customs.id().await);
+        assert_eq!(found.inspector_id, None);
+        assert!(proc.find_declaration(uuid::Uuid::new_v4()).await.is_none());
+
+        let number = decl.registration_number().await.unwrap();
+        let by_number = proc.find_by_number(&number).await.unwrap();
+        assert_eq!(by_number.declaration, found.declaration);
+    }
+
+    #[tokio::test]
+    async fn process_declaration_competence() {
+        let mut proc = Processor::new().await;
+        let location = Location::default();
+        let mut sea = Customs::new("Vladivostok", &location).await;
+        sea.set_competence(Some(Competence::new(&[], &["Sea"], &[]).await))
+            .await;
+        let mut air = Customs::new("Sheremetyevo", &location).await;
+        air.set_competence(Some(Competence::new(&["01", "02"], &["Air"], &[]).await))
+            .await;
+        proc.connect(sea.clone()).await.unwrap();
+        proc.connect(air.clone()).await.unwrap();
+
+        let mut decl = Declaration::<Pending>::default();
+        decl.set_product_code("0201100000")
+            .await
+            .set_transport_type("Air")
+            .await;
+        proc.process_declaration(&decl).await.unwrap();
+        assert_eq!(
+            proc.customs[air.id_ref().await]
+                .declarations_ref()
+                .await
+                .len(),
+            1
+        );
+        assert!(proc.customs[sea.id_ref().await]
+            .declarations_ref()
+            .await
+            .is_empty());
+
+        decl.set_id(uuid::Uuid::new_v4())
+            .await
+            .set_transport_type("Rail")
+            .await;
+        assert!(proc.process_declaration(&decl).await.is_err());
+    }
+
+    /// Customs in UTC+3, which opens `hours` later and works for an hour
+    async fn closed_customs(hours: i64) -> Customs {
+        let mut location = Location::default();
+        location.set_utc_offset(3 * 3600).await;
+        let opens = (Utc::now() + Duration::hours(3) + Duration::hours(hours)).time();
+        let mut customs = Customs::new("Closed", &location).await;
+        customs
+            .set_work_hours(Some((opens, opens + Duration::hours(1))))
+            .await;
+
+        customs
+    }
+
+    #[tokio::test]
+    async fn process_declaration_work_hours() {
+        let mut proc = Processor::new().await;
+        let closed = closed_customs(2).await;
+        let mut open = Customs::new("Open", &Location::default()).await;
+        open.set_work_hours(None).await;
+        proc.connect(closed.clone()).await.unwrap();
+        proc.connect(open.clone()).await.unwrap();
+
+        let decl = Declaration::<Pending>::default();
+        let dispatch = proc.process_declaration(&decl).await.unwrap();
+        assert_eq!(dispatch, Dispatch::Immediate(open.id().await));
+        assert!(proc.customs[closed.id_ref().await]
+            .declarations_ref()
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn process_declaration_deferred() {
+        let mut proc = Processor::new().await;
+        let soon = closed_customs(2).await;
+        let later = closed_customs(5).await;
+        proc.connect(soon.clone()).await.unwrap();
+        proc.connect(later.clone()).await.unwrap();
+
+        let decl = Declaration::<Pending>::default();
+        let dispatch = proc.process_declaration(&decl).await.unwrap();
+        match dispatch {
+            Dispatch::Deferred {
+                customs_id,
+                opens_at,
+            } => {
+                assert_eq!(customs_id, soon.id().await);
+                assert!(opens_at > Utc::now() + Duration::hours(1));
+            }
+            Dispatch::Immediate(_) => panic!("Closed customs picked as open one"),
+            Dispatch::Queued { .. } => panic!("Declaration queued, while customs has room"),
+            Dispatch::Forwarded { .. } => panic!("Declaration forwarded without peers"),
+        }
+    }
+
+    #[tokio::test]
+    async fn process_declaration_load_balanced() {
+        let mut proc = Processor::with_strategy(LeastLoaded).await;
+        let inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut busy = Customs::new("Busy", &Location::default()).await;
+        let mut idle = Customs::new("Idle", &Location::default()).await;
+        for customs in [&mut busy, &mut idle] {
+            customs.set_work_hours(None).await;
+            customs
+                .inspectors_mut()
+                .await
+                .insert(inspector.id().await, inspector.clone());
+        }
+        for _ in 0..2 {
+            let decl: Declaration<Pending> = Declaration::new().await.into();
+            busy.update_decl(decl).await.unwrap();
+        }
+        proc.connect(busy.clone()).await.unwrap();
+        proc.connect(idle.clone()).await.unwrap();
+
+        let decl = Declaration::<Pending>::default();
+        let dispatch = proc.process_declaration(&decl).await.unwrap();
+        assert_eq!(dispatch, Dispatch::Immediate(idle.id().await));
+        let load = proc.customs_load().await;
+        assert!((load[busy.id_ref().await] - 2.0).abs() < f64::EPSILON);
+        assert!((load[idle.id_ref().await] - 1.0).abs() < f64::EPSILON);
+    }
+
+    /// Always picks customs with the given name
+    #[derive(Debug)]
+    struct Named(Uuid);
+
+    impl DispatchStrategy for Named {
+        fn score<'a>(
+            &'a self,
+            _decl: &'a Declaration<Pending>,
+            candidates: &'a [Candidate],
+        ) -> Scores<'a> {
+            let scores = candidates
+                .iter()
+                .map(|candidate| (candidate.id == self.0).then(|| 1.0))
+                .collect();
+            Box::pin(async move { scores })
+        }
+    }
+
+    #[tokio::test]
+    async fn process_declaration_strategy() {
+        let mut first = Customs::new("First", &Location::default()).await;
+        let mut second = Customs::new("Second", &Location::default()).await;
+        first.set_work_hours(None).await;
+        second.set_work_hours(None).await;
+        let mut proc = Processor::with_strategy(Named(second.id().await)).await;
+        proc.connect(first.clone()).await.unwrap();
+        proc.connect(second.clone()).await.unwrap();
+
+        let decl = Declaration::<Pending>::default();
+        let dispatch = proc.process_declaration(&decl).await.unwrap();
+        assert_eq!(dispatch, Dispatch::Immediate(second.id().await));
+
+        proc.set_strategy(Arc::new(Named(Uuid::new_v4()))).await;
+        assert!(proc.process_declaration(&decl).await.is_err());
+
+        proc.set_strategy(Arc::new(RoundRobin::default())).await;
+        let first = proc.process_declaration(&decl).await.unwrap();
+        let second = proc.process_declaration(&decl).await.unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn dispatch_history() {
+        let mut proc = Processor::new().await;
+        let mut open = Customs::new("Open", &Location::default()).await;
+        open.set_work_hours(None).await;
+        let closed = closed_customs(2).await;
+        let mut incompetent = Customs::new("Sea", &Location::default()).await;
+        incompetent
+            .set_competence(Some(Competence::new(&[], &["Sea"], &[]).await))
+            .await;
+        for customs in [&open, &closed, &incompetent] {
+            proc.connect(customs.clone()).await.unwrap();
+        }
+
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        proc.process_declaration(&decl).await.unwrap();
+        proc.process_declaration(&Declaration::new().await.into())
+            .await
+            .unwrap();
+
+        let history = proc.dispatch_history(decl.id().await).await;
+        assert_eq!(history.len(), 1);
+        let record = &history[0];
+        assert_eq!(
+            record.dispatch().await,
+            Some(Dispatch::Immediate(open.id().await))
+        );
+        let candidates = record.candidates_ref().await;
+        let verdict = |id: Uuid| {
+            candidates
+                .iter()
+                .find(|candidate| candidate.customs_id == id)
+                .map(|candidate| candidate.verdict.clone())
+        };
+        assert_eq!(verdict(incompetent.id().await), Some(Verdict::Incompetent));
+        assert!(matches!(
+            verdict(closed.id().await),
+            Some(Verdict::Closed { .. })
+        ));
+        assert!(matches!(verdict(open.id().await), Some(Verdict::Scored(_))));
+
+        // Customs, that refused declaration, is not recorded as its destination
+        let mut strict = Customs::new("Strict", &Location::default()).await;
+        strict
+            .set_work_hours(None)
+            .await
+            .set_require_signatures(true)
+            .await;
+        let mut proc = Processor::new().await;
+        proc.connect(strict).await.unwrap();
+        let unsigned: Declaration<Pending> = Declaration::new().await.into();
+        assert!(proc.process_declaration(&unsigned).await.is_err());
+        let history = proc.dispatch_history(unsigned.id().await).await;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].dispatch().await, None);
+        assert!(history[0].error_ref().await.is_some());
+        assert!(proc.find_declaration(unsigned.id().await).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn drain() {
+        let mut proc = Processor::new().await;
+        let mut maintenance = Customs::new("Maintenance", &Location::default()).await;
+        let mut spare = Customs::new("Spare", &Location::default()).await;
+        maintenance.set_work_hours(None).await;
+        spare.set_work_hours(None).await;
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        maintenance.update_decl(decl.clone()).await.unwrap();
+        proc.connect(maintenance.clone()).await.unwrap();
+
+        // Nowhere to move, declaration stays
+        let report = proc.drain(maintenance.id().await).await.unwrap();
+        assert_eq!(report[0].to, None);
+        assert!(proc.process_declaration(&decl).await.is_err());
+
+        proc.connect(spare.clone()).await.unwrap();
+        let report = proc.drain(maintenance.id().await).await.unwrap();
+        assert_eq!(
+            report,
+            vec![Relocation {
+                declaration_id: decl.id().await,
+                from: maintenance.id().await,
+                to: Some(Dispatch::Immediate(spare.id().await)),
+            }]
+        );
+        assert!(proc.customs[maintenance.id_ref().await]
+            .declarations_ref()
+            .await
+            .is_empty());
+        let history = proc.dispatch_history(decl.id().await).await;
+        assert_eq!(
+            history.last().unwrap().reason_ref().await,
+            &Reason::Drained(maintenance.id().await)
+        );
+
+        proc.resume(maintenance.id().await).await.unwrap();
+        let (customs, report) = proc.disconnect(spare.id().await).await.unwrap();
+        assert_eq!(customs.id().await, spare.id().await);
+        assert_eq!(
+            report[0].to,
+            Some(Dispatch::Immediate(maintenance.id().await))
+        );
+        assert_eq!(proc.customs.len(), 1);
+        assert!(proc.disconnect(spare.id().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn dispatch_queued() {
+        let mut proc = Processor::new().await;
+        let mut regular: Declaration<Pending> = Declaration::new().await.into();
+        regular.set_transport_type("Sea").await;
+        let mut animals: Declaration<Pending> = Declaration::new().await.into();
+        animals
+            .set_priority(Priority::LiveAnimals)
+            .await
+            .set_transport_type("Air")
+            .await;
+        let mut perishable: Declaration<Pending> = Declaration::new().await.into();
+        perishable.set_priority(Priority::Perishable).await;
+
+        assert_eq!(proc.enqueue(regular.clone()).await.unwrap(), 0);
+        assert_eq!(proc.enqueue(animals.clone()).await.unwrap(), 0);
+        assert_eq!(proc.enqueue(perishable.clone()).await.unwrap(), 1);
+
+        let mut air = Customs::new("Air", &Location::default()).await;
+        air.set_work_hours(None)
+            .await
+            .set_competence(Some(Competence::new(&[], &["Air"], &[]).await))
+            .await;
+        // Connecting customs releases the queue on its own
+        proc.connect(air.clone()).await.unwrap();
+        let history = proc.dispatch_history(animals.id().await).await;
+        assert_eq!(
+            history.last().unwrap().dispatch().await,
+            Some(Dispatch::Immediate(air.id().await))
+        );
+        assert_eq!(
+            history.last().unwrap().reason_ref().await,
+            &Reason::Dequeued
+        );
+        let report = proc.dispatch_queued().await;
+        assert_eq!(
+            report,
+            vec![(perishable.id().await, None), (regular.id().await, None)]
+        );
+        assert_eq!(proc.intake_ref().await.len().await, 2);
+
+        let mut any = Customs::new("Any", &Location::default()).await;
+        any.set_work_hours(None).await;
+        proc.connect(any).await.unwrap();
+        assert!(proc.intake_ref().await.is_empty().await);
+        assert!(proc.dispatch_queued().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn process_declaration_capacity() {
+        let mut proc = Processor::new().await;
+        let inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut customs = Customs::new("Small", &Location::default()).await;
+        customs
+            .set_work_hours(None)
+            .await
+            .set_capacity(Some(1))
+            .await
+            .inspectors_mut()
+            .await
+            .insert(inspector.id().await, inspector.clone());
+        let customs_id = customs.id().await;
+        proc.connect(customs).await.unwrap();
+
+        let first: Declaration<Pending> = Declaration::new().await.into();
+        let second: Declaration<Pending> = Declaration::new().await.into();
+        let mut urgent: Declaration<Pending> = Declaration::new().await.into();
+        urgent.set_priority(Priority::Perishable).await;
+        assert_eq!(
+            proc.process_declaration(&first).await.unwrap(),
+            Dispatch::Immediate(customs_id)
+        );
+        assert_eq!(
+            proc.process_declaration(&second).await.unwrap(),
+            Dispatch::Queued { position: 0 }
+        );
+        assert_eq!(
+            proc.process_declaration(&urgent).await.unwrap(),
+            Dispatch::Queued { position: 0 }
+        );
+        let record = proc.dispatch_history(second.id().await).await.remove(0);
+        assert_eq!(record.candidates_ref().await[0].verdict, Verdict::Full);
+
+        // Inspector takes the first declaration, freeing the room for the most urgent one
+        proc.assign_next(customs_id, inspector.id().await)
+            .await
+            .unwrap();
+        let found = proc.find_declaration(urgent.id().await).await.unwrap();
+        assert_eq!(found.customs_id, customs_id);
+        assert_eq!(proc.intake_ref().await.ids().await, vec![second.id().await]);
+
+        proc.remove_declaration(urgent.id().await).await;
+        assert!(proc.intake_ref().await.is_empty().await);
+        assert!(proc.find_declaration(second.id().await).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn find_declaration_index() {
+        let mut proc = Processor::new().await;
+        let inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        customs
+            .inspectors_mut()
+            .await
+            .insert(inspector.id().await, inspector.clone());
+        let mut spare = Customs::new("Spare", &Location::default()).await;
+        spare.set_work_hours(None).await;
+        let held: Declaration<Pending> = Declaration::new().await.into();
+        spare.update_decl(held.clone()).await.unwrap();
+        proc.connect(customs.clone()).await.unwrap();
+        proc.connect(spare.clone()).await.unwrap();
+        proc.drain(spare.id().await).await.unwrap();
+
+        // Already held declarations are indexed on connect, moved ones on dispatch
+        let found = proc.find_declaration(held.id().await).await.unwrap();
+        assert_eq!(found.customs_id, customs.id().await);
+
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        proc.process_declaration(&decl).await.unwrap();
+        proc.assign_next(customs.id().await, inspector.id().await)
+            .await
+            .unwrap();
+        let found = proc.find_declaration(held.id().await).await.unwrap();
+        assert_eq!(found.inspector_id, Some(inspector.id().await));
+        assert!(matches!(
+            found.declaration,
+            DeclarationGeneric::Inspecting(_)
+        ));
+
+        assert!(matches!(
+            proc.remove_declaration(held.id().await).await,
+            Some(DeclarationGeneric::Inspecting(_))
+        ));
+        assert!(proc.find_declaration(held.id().await).await.is_none());
+
+        proc.disconnect(customs.id().await).await.unwrap();
+        assert!(proc.find_declaration(decl.id().await).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn decide() {
+        use crate::models::participants::{declarant::Declarant, Participant};
+
+        let mut proc = Processor::new().await;
+        let inspector = Inspector::new("Ivan", "Inspector", "Leutenant").await;
+        let mut customs = Customs::new("Moscow", &Location::default()).await;
+        customs.set_work_hours(None).await;
+        customs
+            .inspectors_mut()
+            .await
+            .insert(inspector.id().await, inspector.clone());
+        let customs_id = customs.id().await;
+        proc.connect(customs).await.unwrap();
+        let mut declarant = Declarant::new("John").await;
+        let (approved, rejected): (Declaration<Pending>, Declaration<Pending>) = (
+            Declaration::new().await.into(),
+            Declaration::new().await.into(),
+        );
+        for decl in [&approved, &rejected] {
+            declarant
+                .update_declaration(&DeclarationGeneric::Pending(decl.clone()))
+                .await
+                .unwrap();
+            proc.process_declaration(decl).await.unwrap();
+            proc.assign_next(customs_id, inspector.id().await)
+                .await
+                .unwrap();
+        }
+
+        proc.approve(&mut declarant, approved.id().await, "Documents are correct")
+            .await
+            .unwrap();
+        proc.reject(&mut declarant, rejected.id().await, "Goods are banned")
+            .await
+            .unwrap();
+        for decl in [&approved, &rejected] {
+            assert!(proc.find_declaration(decl.id().await).await.is_none());
+        }
+        assert!(proc.customs_ref().await[&customs_id]
+            .declaration_ids()
+            .await
+            .is_empty());
+        assert!(matches!(
+            declarant.get_declaration(approved.id().await).await,
+            Some(DeclarationGeneric::Approved(_))
+        ));
+        assert!(matches!(
+            declarant.get_declaration(rejected.id().await).await,
+            Some(DeclarationGeneric::Rejected(_))
+        ));
+        assert!(proc
+            .approve(&mut declarant, approved.id().await, "Again")
+            .await
+            .is_err());
+
+        // Declaration waiting for amendment stays at customs and goes back to the same inspector
+        let amended: Declaration<Pending> = Declaration::new().await.into();
+        let id = amended.id().await;
+        declarant
+            .update_declaration(&DeclarationGeneric::Pending(amended.clone()))
+            .await
+            .unwrap();
+        proc.process_declaration(&amended).await.unwrap();
+        proc.assign_next(customs_id, inspector.id().await)
+            .await
+            .unwrap();
+        proc.request_info(&mut declarant, id, &["sender_name"], "Who sends it?")
+            .await
+            .unwrap();
+        let found = proc.find_declaration(id).await.unwrap();
+        assert!(matches!(
+            found.declaration,
+            DeclarationGeneric::InfoRequested(_)
+        ));
+        assert_eq!(found.inspector_id, Some(inspector.id().await));
+        declarant
+            .amend_declaration(id, "sender_name", "Jane")
+            .await
+            .unwrap();
+        declarant.resubmit(&mut proc, id).await.unwrap();
+        assert!(matches!(
+            declarant.get_declaration(id).await,
+            Some(DeclarationGeneric::Inspecting(_))
+        ));
+        assert!(matches!(
+            proc.find_declaration(id).await.unwrap().declaration,
+            DeclarationGeneric::Inspecting(_)
+        ));
+        assert!(declarant.resubmit(&mut proc, id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn redispatch_stale() {
+        let mut slow = Customs::new("Slow", &Location::default()).await;
+        let mut spare = Customs::new("Spare", &Location::default()).await;
+        slow.set_work_hours(None).await;
+        spare.set_work_hours(None).await;
+        let mut proc = Processor::with_strategy(Named(slow.id().await)).await;
+        proc.connect(slow.clone()).await.unwrap();
+        proc.connect(spare.clone()).await.unwrap();
+        let decl: Declaration<Pending> = Declaration::new().await.into();
+        proc.process_declaration(&decl).await.unwrap();
+        assert!(proc.redispatch_stale(Utc::now()).await.is_empty());
+
+        proc.set_sla(Some(Duration::minutes(10))).await;
+        assert!(proc.redispatch_stale(Utc::now()).await.is_empty());
+        proc.set_strategy(Arc::new(RoundRobin::default())).await;
+        let report = proc
+            .redispatch_stale(Utc::now() + Duration::minutes(11))
+            .await;
+        assert_eq!(
+            report,
+            vec![Relocation {
+                declaration_id: decl.id().await,
+                from: slow.id().await,
+                to: Some(Dispatch::Immediate(spare.id().await)),
+            }]
+        );
+        let record = proc.dispatch_history(decl.id().await).await.pop().unwrap();
+        assert_eq!(
+            record.reason_ref().await,
+            &Reason::SlaExpired(slow.id().await)
+        );
+        let found = proc.find_declaration(decl.id().await).await.unwrap();
+        assert_eq!(found.customs_id, spare.id().await);
+        let DeclarationGeneric::Pending(moved) = found.declaration else {
+            panic!("Declaration is not pending anymore");
+        };
+        assert!(moved.state_entered_at().await > decl.state_entered_at().await);
+
+        // Declaration waits less where it is, than in customs, that is closed
+        proc.disconnect(slow.id().await).await.unwrap();
+        proc.connect(closed_customs(2).await).await.unwrap();
+        let report = proc
+            .redispatch_stale(Utc::now() + Duration::minutes(11))
+            .await;
+        assert_eq!(
+            report,
+            vec![Relocation {
+                declaration_id: decl.id().await,
+                from: spare.id().await,
+                to: None,
+            }]
+        );
+        let found = proc.find_declaration(decl.id().await).await.unwrap();
+        assert_eq!(found.customs_id, spare.id().await);
+    }
+}
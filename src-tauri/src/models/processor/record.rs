@@ -0,0 +1,88 @@
+//! Dispatch records, explaining why declaration went to particular customs
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::Dispatch;
+use crate::{prelude::*, utils::HasId};
+
+/// Why declaration was dispatched
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Reason {
+    /// Declaration was sent to Processor
+    Submitted,
+    /// Customs with the given id is drained, its declarations are moved elsewhere
+    Drained(Uuid),
+    /// Declaration waited in Processor's queue
+    Dequeued,
+    /// Declaration was pending at customs with the given id longer than SLA allows
+    SlaExpired(Uuid),
+    /// Peer Processor with the given id couldn't process declaration
+    Forwarded(Uuid),
+}
+
+/// What Processor thought of a customs while dispatching declaration
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Verdict {
+    /// Customs is not competent to process declaration
+    Incompetent,
+    /// Customs is not considered at all, e.g. it already failed to process declaration in time
+    Excluded,
+    /// Customs is being drained, no new declarations are routed to it
+    Draining,
+    /// Customs has reached its capacity
+    Full,
+    /// Customs is closed at the moment
+    Closed { opens_at: DateTime<Utc> },
+    /// Customs was ranked by dispatch strategy
+    Scored(f64),
+    /// Dispatch strategy excluded customs
+    Rejected,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    pub customs_id: Uuid,
+    pub verdict: Verdict,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DispatchRecord {
+    #[serde(skip)]
+    id: Uuid,
+    declaration_id: Uuid,
+    reason: Reason,
+    candidates: Vec<Candidate>,
+    /// None, if declaration wasn't dispatched
+    dispatch: Option<Dispatch>,
+    error: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl DispatchRecord {
+    pub async fn new(declaration_id: Uuid, reason: Reason, created_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            declaration_id,
+            reason,
+            candidates: Vec::default(),
+            dispatch: None,
+            error: None,
+            created_at,
+        }
+    }
+}
+
+/// Boilerplate
+impl DispatchRecord {
+    getter_ref!( { async } id: &Uuid, { async } declaration_id: &Uuid, { async } reason: &Reason, { async } candidates: &Vec<Candidate>, { async } dispatch: &Option<Dispatch>, { async } error: &Option<String>, { async } created_at: &DateTime<Utc>);
+    getter_mut!( { async } candidates: &mut Vec<Candidate>);
+    setter!( { async } dispatch: Option<Dispatch>, { async } error: Option<String>);
+    getter!( { async } id: Uuid, { async } declaration_id: Uuid, { async } dispatch: Option<Dispatch>, { async } created_at: DateTime<Utc>);
+}
+
+impl HasId for DispatchRecord {
+    fn id(&mut self) -> &mut Uuid {
+        &mut self.id
+    }
+}
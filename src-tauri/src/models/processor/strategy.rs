@@ -0,0 +1,317 @@
+//! Dispatch strategies, used by Processor to pick customs for declaration.
+//!
+//! Processor itself filters out incompetent customs and takes care of work hours,
+//! strategy only ranks the customs left.
+
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::Future;
+use uuid::Uuid;
+
+use crate::models::{
+    customs::{competence::Competence, logic::Logic as CustomsLogic, Customs},
+    declaration::{Declaration, Pending},
+    misc::location::Location,
+};
+use crate::prelude::*;
+
+/// Scores of candidates in the same order as candidates were given
+pub type Scores<'a> = Pin<Box<dyn Future<Output = Vec<Option<f64>>> + Send + 'a>>;
+
+pub trait DispatchStrategy: Debug + Send + Sync {
+    /// Score every candidate, the one with the highest score gets the declaration.
+    /// `None` excludes candidate from dispatch
+    fn score<'a>(
+        &'a self,
+        decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a>;
+}
+
+/// Snapshot of customs, passed to dispatch strategy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub id: Uuid,
+    pub competence: Option<Competence>,
+    pub location: Option<Location>,
+    /// Pending declarations per inspector, see `Customs::load`
+    pub load: f64,
+}
+
+impl Candidate {
+    pub async fn new(customs: &Customs) -> Self {
+        Self {
+            id: customs.id().await,
+            competence: customs.competence().await,
+            location: customs.location_ref().await.clone(),
+            load: customs.load().await,
+        }
+    }
+}
+
+/// Pick a random customs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Random;
+
+impl DispatchStrategy for Random {
+    fn score<'a>(
+        &'a self,
+        _decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a> {
+        let scores = candidates.iter().map(|_| Some(rand::random())).collect();
+        Box::pin(async move { scores })
+    }
+}
+
+/// Pick customs one after another. Candidates are ordered by id,
+/// so the order is stable as long as the set of customs doesn't change
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    cursor: AtomicUsize,
+}
+
+impl DispatchStrategy for RoundRobin {
+    fn score<'a>(
+        &'a self,
+        _decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a> {
+        let turn = match candidates.len() {
+            0 => 0,
+            len => self.cursor.fetch_add(1, Ordering::Relaxed) % len,
+        };
+        let scores = (0..candidates.len())
+            .map(|i| Some(if i == turn { 1.0 } else { 0.0 }))
+            .collect();
+        Box::pin(async move { scores })
+    }
+}
+
+/// Accept only customs competent to process declaration, without ranking them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Competent;
+
+impl DispatchStrategy for Competent {
+    fn score<'a>(
+        &'a self,
+        decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a> {
+        Box::pin(async move {
+            let mut scores = Vec::with_capacity(candidates.len());
+            for candidate in candidates {
+                let competent = match &candidate.competence {
+                    Some(competence) => competence.covers(decl).await,
+                    None => true,
+                };
+                scores.push(if competent { Some(1.0) } else { None });
+            }
+
+            scores
+        })
+    }
+}
+
+/// Pick customs with the lowest pending declarations per inspector ratio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LeastLoaded;
+
+impl DispatchStrategy for LeastLoaded {
+    fn score<'a>(
+        &'a self,
+        _decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a> {
+        let scores = candidates
+            .iter()
+            .map(|candidate| Some(-candidate.load))
+            .collect();
+        Box::pin(async move { scores })
+    }
+}
+
+/// Where the goods or the declarant are, from Processor's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionSource {
+    /// Region where declarant is registered as a tax payer
+    Registration,
+    /// Region of goods, i.e. declaration departure or destination
+    Goods,
+}
+
+/// Prefer customs located in the same region as the declarant or the goods.
+/// Sources are checked in the given order, earlier match gives higher score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regional {
+    order: Vec<RegionSource>,
+}
+
+impl Regional {
+    pub async fn new(order: &[RegionSource]) -> Self {
+        Self {
+            order: order.to_vec(),
+        }
+    }
+}
+
+impl Default for Regional {
+    fn default() -> Self {
+        Self {
+            order: vec![RegionSource::Registration, RegionSource::Goods],
+        }
+    }
+}
+
+impl DispatchStrategy for Regional {
+    #[allow(clippy::cast_precision_loss)]
+    fn score<'a>(
+        &'a self,
+        decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a> {
+        Box::pin(async move {
+            let registration = decl.registration_region_ref().await;
+            let goods = [decl.departure_ref().await, decl.destination_ref().await];
+            let mut scores = Vec::with_capacity(candidates.len());
+            for candidate in candidates {
+                let region = match &candidate.location {
+                    Some(location) => location.region_ref().await,
+                    None => "",
+                };
+                let matches = |source: &RegionSource| {
+                    !region.is_empty()
+                        && match source {
+                            RegionSource::Registration => region.eq_ignore_ascii_case(registration),
+                            RegionSource::Goods => {
+                                goods.iter().any(|place| region.eq_ignore_ascii_case(place))
+                            }
+                        }
+                };
+                let score = self
+                    .order
+                    .iter()
+                    .position(matches)
+                    .map_or(0, |i| self.order.len() - i);
+                scores.push(Some(score as f64));
+            }
+
+            scores
+        })
+    }
+}
+
+/// Weighted sum of other strategies' scores.
+/// Candidate excluded by any of the strategies is excluded from the result as well
+#[derive(Debug, Clone, Default)]
+pub struct Weighted {
+    strategies: Vec<(f64, Arc<dyn DispatchStrategy>)>,
+}
+
+impl Weighted {
+    pub async fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add strategy with the given weight
+    pub async fn with(mut self, weight: f64, strategy: impl DispatchStrategy + 'static) -> Self {
+        self.strategies.push((weight, Arc::new(strategy)));
+        self
+    }
+}
+
+impl DispatchStrategy for Weighted {
+    fn score<'a>(
+        &'a self,
+        decl: &'a Declaration<Pending>,
+        candidates: &'a [Candidate],
+    ) -> Scores<'a> {
+        Box::pin(async move {
+            let mut total = vec![Some(0.0); candidates.len()];
+            for (weight, strategy) in &self.strategies {
+                let scores = strategy.score(decl, candidates).await;
+                for (total, score) in total.iter_mut().zip(scores) {
+                    *total = total
+                        .zip(score)
+                        .map(|(total, score)| total + weight * score);
+                }
+            }
+
+            total
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(load: f64) -> Candidate {
+        Candidate {
+            id: Uuid::new_v4(),
+            competence: None,
+            location: None,
+            load,
+        }
+    }
+
+    #[tokio::test]
+    async fn round_robin() {
+        let strategy = RoundRobin::default();
+        let decl = Declaration::<Pending>::default();
+        let candidates = [candidate(0.0), candidate(0.0)];
+
+        let first = strategy.score(&decl, &candidates).await;
+        let second = strategy.score(&decl, &candidates).await;
+        let third = strategy.score(&decl, &candidates).await;
+        assert_eq!(first, vec![Some(1.0), Some(0.0)]);
+        assert_eq!(second, vec![Some(0.0), Some(1.0)]);
+        assert_eq!(first, third);
+    }
+
+    #[tokio::test]
+    async fn regional() {
+        let mut decl = Declaration::<Pending>::default();
+        decl.set_registration_region("Moscow")
+            .await
+            .set_destination("Primorsky Krai")
+            .await;
+        let mut candidates = [candidate(0.0), candidate(0.0), candidate(0.0)];
+        for (candidate, region) in candidates
+            .iter_mut()
+            .zip(["moscow", "Primorsky Krai", "Tver"])
+        {
+            let mut location = Location::default();
+            location.set_region(region).await;
+            candidate.location = Some(location);
+        }
+
+        let scores = Regional::default().score(&decl, &candidates).await;
+        assert_eq!(scores, vec![Some(2.0), Some(1.0), Some(0.0)]);
+
+        let strategy = Regional::new(&[RegionSource::Goods, RegionSource::Registration]).await;
+        let scores = strategy.score(&decl, &candidates).await;
+        assert_eq!(scores, vec![Some(1.0), Some(2.0), Some(0.0)]);
+    }
+
+    #[tokio::test]
+    async fn weighted() {
+        let decl = Declaration::<Pending>::default();
+        let mut incompetent = candidate(0.0);
+        incompetent.competence = Some(Competence::new(&["99"], &[], &[]).await);
+        let candidates = [candidate(2.0), candidate(f64::INFINITY), incompetent];
+
+        let strategy = Weighted::new()
+            .await
+            .with(1.0, Competent)
+            .await
+            .with(2.0, LeastLoaded)
+            .await;
+        let scores = strategy.score(&decl, &candidates).await;
+        assert_eq!(scores[0], Some(-3.0));
+        assert!(scores[1].unwrap() < scores[0].unwrap());
+        assert_eq!(scores[2], None);
+    }
+}
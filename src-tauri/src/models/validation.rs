@@ -0,0 +1,403 @@
+//! Validation rules of declaration fields.
+//!
+//! Rules work on named fields of declaration (see `Declaration::named_fields`),
+//! `[*]` in a field name stands for every goods line, e.g. `goods[*].value`.
+//! Validator checks every rule and reports all violations at once.
+
+use std::collections::BTreeMap;
+
+use crate::errors::declaration::Err as DErr;
+use crate::models::classifier::Classifier;
+use crate::models::declaration::Declaration;
+use crate::prelude::*;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Format {
+    /// Exactly that many digits
+    Digits(usize),
+    /// Exactly that many latin capital letters, e.g. currency or country code
+    Letters(usize),
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Rule {
+    /// Field must not be empty
+    Required(String),
+    /// Field must be a number greater than zero
+    Positive(String),
+    /// Field must be a number within bounds, bounds included
+    Range { field: String, min: f64, max: f64 },
+    /// Field must match format. Empty field matches any format, use `Required` for it
+    Format { field: String, format: Format },
+    /// Fields must differ, case and surrounding spaces are ignored
+    Distinct(String, String),
+    /// Field must not be greater than the other one, e.g. net weight than gross weight.
+    /// Fields with `[*]` are compared on the same goods line
+    NotGreater { field: String, than: String },
+    /// Field must be a commodity code, that classifier knows. Empty field is skipped, use `Required` for it
+    Classified {
+        field: String,
+        classifier: Arc<Classifier>,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Validator {
+    rules: Vec<Rule>,
+}
+
+impl Validator {
+    pub async fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Default rules, and commodity codes must be known to classifier
+    pub async fn classified(classifier: Arc<Classifier>) -> Self {
+        let mut validator = Self::default();
+        validator
+            .add_rule(Rule::Classified {
+                field: "goods[*].code".to_string(),
+                classifier,
+            })
+            .await;
+
+        validator
+    }
+}
+
+impl Default for Validator {
+    /// Rules every declaration must satisfy to be sent
+    fn default() -> Self {
+        let required = [
+            "transport_type",
+            "transport_name",
+            "sender_name",
+            "receiver_name",
+            "destination",
+            "departure",
+            "goods[*].code",
+            "goods[*].description",
+        ];
+        // Lower bound of `MIN_POSITIVE` rejects zero, so a bad value isn't reported by `Positive` too
+        let ranges = [
+            ("goods[*].quantity", f64::MIN_POSITIVE, 1e9),
+            ("goods[*].gross_weight", f64::MIN_POSITIVE, 1e7),
+            ("goods[*].net_weight", 0.0, 1e7),
+            ("goods[*].value", f64::MIN_POSITIVE, 1e12),
+        ];
+        let mut rules: Vec<Rule> = required
+            .iter()
+            .map(|field| Rule::Required(field.to_string()))
+            .chain(ranges.iter().map(|(field, min, max)| Rule::Range {
+                field: field.to_string(),
+                min: *min,
+                max: *max,
+            }))
+            .collect();
+        rules.extend([
+            Rule::Format {
+                field: "currency".to_string(),
+                format: Format::Letters(3),
+            },
+            Rule::Format {
+                field: "goods[*].origin".to_string(),
+                format: Format::Letters(2),
+            },
+            Rule::Distinct("departure".to_string(), "destination".to_string()),
+            Rule::NotGreater {
+                field: "goods[*].net_weight".to_string(),
+                than: "goods[*].gross_weight".to_string(),
+            },
+        ]);
+
+        Self { rules }
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use super::*;
+
+    impl super::Validator {
+        /// Every violation of every rule, as `InvalidField`. Empty, if declaration is valid
+        pub async fn check<State>(&self, decl: &Declaration<State>) -> Vec<DErr> {
+            let id = decl.id().await;
+            let fields = decl.named_fields().await;
+            let mut violations = Vec::new();
+            for rule in &self.rules {
+                for (field, value) in rule.violations(&fields).await {
+                    violations.push(DErr::InvalidField { id, field, value });
+                }
+            }
+
+            violations
+        }
+
+        pub async fn add_rule(&mut self, rule: Rule) -> &mut Self {
+            self.rules.push(rule);
+
+            self
+        }
+    }
+
+    impl super::Rule {
+        /// Names and values of fields, that break the rule
+        async fn violations(&self, fields: &BTreeMap<String, String>) -> Vec<(String, String)> {
+            let mut violations = Vec::new();
+            let mut check = |field: &String, value: &String, valid: bool| {
+                if !valid {
+                    violations.push((field.clone(), value.clone()));
+                }
+            };
+            match self {
+                Self::Required(pattern) => {
+                    for (field, value) in matching(fields, pattern) {
+                        check(field, value, !value.trim().is_empty());
+                    }
+                }
+                Self::Positive(pattern) => {
+                    for (field, value) in matching(fields, pattern) {
+                        let number = value.parse::<f64>();
+                        check(field, value, number.map_or(false, |n| n > 0.0));
+                    }
+                }
+                Self::Range {
+                    field: pattern,
+                    min,
+                    max,
+                } => {
+                    for (field, value) in matching(fields, pattern) {
+                        let number = value.parse::<f64>();
+                        check(
+                            field,
+                            value,
+                            number.map_or(false, |n| (*min..=*max).contains(&n)),
+                        );
+                    }
+                }
+                Self::Format {
+                    field: pattern,
+                    format,
+                } => {
+                    for (field, value) in matching(fields, pattern) {
+                        check(field, value, value.is_empty() || format.matches(value));
+                    }
+                }
+                Self::Distinct(first, second) => {
+                    for (field, value) in matching(fields, first) {
+                        let other = fields.get(&counterpart(pattern_of(first), second, field));
+                        let same = other.map_or(false, |other| {
+                            !value.trim().is_empty()
+                                && value.trim().eq_ignore_ascii_case(other.trim())
+                        });
+                        check(field, value, !same);
+                    }
+                }
+                Self::NotGreater {
+                    field: pattern,
+                    than,
+                } => {
+                    for (field, value) in matching(fields, pattern) {
+                        let other = fields.get(&counterpart(pattern_of(pattern), than, field));
+                        let greater = match (value.parse::<f64>(), other.map(|o| o.parse::<f64>()))
+                        {
+                            (Ok(value), Some(Ok(other))) => value > other,
+                            _ => false,
+                        };
+                        check(field, value, !greater);
+                    }
+                }
+                Self::Classified {
+                    field: pattern,
+                    classifier,
+                } => {
+                    for (field, value) in matching(fields, pattern) {
+                        let known = value.is_empty() || classifier.check(value).await.is_ok();
+                        check(field, value, known);
+                    }
+                }
+            }
+
+            violations
+        }
+    }
+
+    impl super::Format {
+        fn matches(&self, value: &str) -> bool {
+            match self {
+                Self::Digits(len) => {
+                    value.len() == *len && value.chars().all(|c| c.is_ascii_digit())
+                }
+                Self::Letters(len) => {
+                    value.len() == *len && value.chars().all(|c| c.is_ascii_uppercase())
+                }
+            }
+        }
+    }
+
+    /// Prefix and suffix of a pattern around `[*]`, None for plain field name
+    fn pattern_of(pattern: &str) -> Option<(&str, &str)> {
+        pattern.split_once("[*]")
+    }
+
+    /// Fields matching pattern, every goods line for patterns with `[*]`
+    fn matching<'a>(
+        fields: &'a BTreeMap<String, String>,
+        pattern: &'a str,
+    ) -> Vec<(&'a String, &'a String)> {
+        match pattern_of(pattern) {
+            Some((prefix, suffix)) => fields
+                .iter()
+                .filter(|(field, _)| line_of(field, prefix, suffix).is_some())
+                .collect(),
+            None => fields.get_key_value(pattern).into_iter().collect(),
+        }
+    }
+
+    /// Line number of field, that matches pattern parts
+    fn line_of<'a>(field: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+        let line = field
+            .strip_prefix(prefix)?
+            .strip_prefix('[')?
+            .strip_suffix(suffix)?
+            .strip_suffix(']')?;
+
+        (!line.is_empty() && line.chars().all(|c| c.is_ascii_digit())).then(|| line)
+    }
+
+    /// Name of the other field of a pair rule, on the same goods line as the given field
+    fn counterpart(pattern: Option<(&str, &str)>, other: &str, field: &str) -> String {
+        pattern
+            .and_then(|(prefix, suffix)| line_of(field, prefix, suffix))
+            .map_or_else(
+                || other.to_string(),
+                |line| other.replacen("[*]", &format!("[{line}]"), 1),
+            )
+    }
+}
+
+/// Boilerplate
+impl Validator {
+    getter_ref!( { async } rules: &Vec<Rule>);
+    setter!( { async } rules: Vec<Rule>);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Format, Rule, Validator};
+    use crate::errors::declaration::Err as DErr;
+    use crate::models::{
+        classifier::Classifier,
+        declaration::{Declaration, Draft},
+        misc::goods::GoodsItem,
+    };
+
+    fn invalid_fields(violations: &[DErr]) -> Vec<&str> {
+        violations
+            .iter()
+            .filter_map(|violation| match violation {
+                DErr::InvalidField { field, .. } => Some(field.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn default_rules() {
+        let mut decl = Declaration::<Draft>::new().await;
+        decl.set_transport_type("Truck")
+            .await
+            .set_transport_name("Volvo")
+            .await
+            .set_sender_name("John")
+            .await
+            .set_receiver_name("Jane")
+            .await
+            .set_departure("Moscow")
+            .await
+            .set_destination(" moscow ")
+            .await
+            .set_currency("usd")
+            .await;
+        let mut horse = GoodsItem::new("0101210000", "Horse", 2.0, "pcs").await;
+        horse
+            .set_gross_weight(1000.0)
+            .await
+            .set_net_weight(1100.0)
+            .await
+            .set_value(5000.0)
+            .await
+            .set_origin("DE")
+            .await;
+        let mut cheese = GoodsItem::new("0406", "", -1.0, "kg").await;
+        cheese.set_gross_weight(11.0).await.set_value(300.0).await;
+        decl.set_goods(vec![horse, cheese]).await;
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tnved.json");
+        let classifier = Classifier::load(path).await.unwrap();
+        let validator = Validator::classified(Arc::new(classifier)).await;
+        let violations = validator.check(&decl).await;
+        // Every wrong field is reported at once
+        assert_eq!(
+            invalid_fields(&violations),
+            [
+                "goods[1].description",
+                "goods[1].quantity",
+                "currency",
+                "departure",
+                "goods[0].net_weight",
+                "goods[1].code",
+            ]
+        );
+        assert!(decl.validate().await.is_err());
+
+        decl.set_destination("Kazan")
+            .await
+            .set_currency("USD")
+            .await;
+        decl.goods_mut().await[0].set_net_weight(900.0).await;
+        decl.goods_mut().await[1]
+            .set_code("0406105000")
+            .await
+            .set_description("Cheese")
+            .await
+            .set_quantity(10.0)
+            .await;
+        assert!(validator.check(&decl).await.is_empty());
+        assert!(decl.validate().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn custom_rules() {
+        let mut decl = Declaration::<Draft>::new().await;
+        decl.set_product_code("12AB").await;
+        decl.set_product_price(50.0).await;
+        let mut validator = Validator::new(Vec::new()).await;
+        validator
+            .add_rule(Rule::Format {
+                field: "goods[*].code".to_string(),
+                format: Format::Digits(10),
+            })
+            .await
+            .add_rule(Rule::Range {
+                field: "goods[*].value".to_string(),
+                min: 100.0,
+                max: 1000.0,
+            })
+            .await;
+
+        let violations = validator.check(&decl).await;
+        assert_eq!(
+            invalid_fields(&violations),
+            ["goods[0].code", "goods[0].value"]
+        );
+    }
+}
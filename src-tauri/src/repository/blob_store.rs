@@ -0,0 +1,141 @@
+//! Local content-addressed store of attachment files.
+//! Content is kept in a file named after its SHA-256 hash, so the same file is stored once
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::prelude::*;
+
+/// Hex encoded SHA-256 of content
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    /// Store in the directory, directory is created if it doesn't exist
+    #[tracing::instrument(skip(root))]
+    pub async fn open(root: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let root = root.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&root).await?;
+        tracing::info!("Blob store opened at {}", root.display());
+
+        Ok(Self { root })
+    }
+}
+
+///
+/// We Hide Business Logic behind separate module.
+/// We do this in order to if we want to turn current Structs
+/// into DTO Structs (or just strip it out of said logic).
+///
+/// Import Logic: ``use <path>::<struct>::logic::*;``
+///
+mod logic {
+    use std::error::Error;
+
+    use super::content_hash;
+    use crate::errors::attachment::Err as AErr;
+
+    impl super::BlobStore {
+        /// Save content, returns its hash
+        pub async fn put(&self, content: &[u8]) -> Result<String, Box<dyn Error>> {
+            let hash = content_hash(content);
+            let path = self.root.join(&hash);
+            if !tokio::fs::try_exists(&path).await? {
+                tokio::fs::write(&path, content).await?;
+                tracing::info!("Blob {} saved, {} bytes", hash, content.len());
+            }
+
+            Ok(hash)
+        }
+
+        /// Content with the hash. Content is checked against the hash before it is given away
+        pub async fn get(&self, hash: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+            let path = self
+                .path_of(hash)
+                .ok_or_else(|| AErr::BlobNotFound(hash.to_string()))?;
+            let content = match tokio::fs::read(&path).await {
+                Ok(content) => content,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(Box::new(AErr::BlobNotFound(hash.to_string())));
+                }
+                Err(err) => return Err(Box::new(err)),
+            };
+            let actual = content_hash(&content);
+            if !actual.eq_ignore_ascii_case(hash) {
+                tracing::warn!("Blob {} is damaged", hash);
+                return Err(Box::new(AErr::HashMismatch {
+                    expected: hash.to_string(),
+                    actual,
+                }));
+            }
+
+            Ok(content)
+        }
+
+        pub async fn contains(&self, hash: &str) -> bool {
+            match self.path_of(hash) {
+                Some(path) => tokio::fs::try_exists(path).await.unwrap_or(false),
+                None => false,
+            }
+        }
+    }
+}
+
+/// Private methods
+impl BlobStore {
+    /// Path of content with the hash. None for anything, that isn't a hash,
+    /// so names like `../secret` never leave the store
+    fn path_of(&self, hash: &str) -> Option<PathBuf> {
+        let is_hash = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+
+        is_hash.then(|| self.root.join(hash.to_ascii_lowercase()))
+    }
+}
+
+/// Boilerplate
+impl BlobStore {
+    getter_ref!( { async } root: &PathBuf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlobStore;
+    use crate::errors::attachment::Err as AErr;
+
+    #[tokio::test]
+    async fn put_get() {
+        let root = std::env::temp_dir().join(format!("blobs-{}", uuid::Uuid::new_v4()));
+        let store = BlobStore::open(&root).await.unwrap();
+
+        let hash = store.put(b"invoice #1").await.unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(store.put(b"invoice #1").await.unwrap(), hash);
+        assert!(store.contains(&hash).await);
+        assert_eq!(store.get(&hash).await.unwrap(), b"invoice #1");
+
+        let missing = store.get(&"0".repeat(64)).await.unwrap_err();
+        assert!(matches!(
+            missing.downcast_ref::<AErr>(),
+            Some(AErr::BlobNotFound(_))
+        ));
+        assert!(store.get("../invoice").await.is_err());
+
+        tokio::fs::write(root.join(&hash), b"invoice #2")
+            .await
+            .unwrap();
+        let damaged = store.get(&hash).await.unwrap_err();
+        assert!(matches!(
+            damaged.downcast_ref::<AErr>(),
+            Some(AErr::HashMismatch { .. })
+        ));
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
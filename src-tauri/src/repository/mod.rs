@@ -1,6 +1,7 @@
 use std::error::Error;
 use uuid::Uuid;
 
+pub mod blob_store;
 mod surreal_structs;
 pub mod surrealdb;
 
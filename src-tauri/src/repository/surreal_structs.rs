@@ -2,10 +2,14 @@ use std::fmt::Debug;
 
 use crate::errors::declaration::Err as DErr;
 use crate::models::declaration::{
-    Approved, Declaration, DeclarationGeneric, Draft, Inspecting, Pending, Rejected,
+    Approved, Decision, Declaration, DeclarationGeneric, Draft, InfoRequest, InfoRequested,
+    Inspecting, Pending, Priority, Rejected, Revision,
 };
-use chrono::serde::ts_seconds;
-use chrono::{DateTime, Utc};
+use crate::models::misc::attachment::Attachment;
+use crate::models::misc::goods::GoodsItem;
+use crate::models::misc::registration::RegistrationNumber;
+use crate::models::misc::signature::Signature;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use surrealdb::sql::Thing;
@@ -22,8 +26,8 @@ pub struct SurrealLocation {
     pub country: String,
     pub region: String,
     pub city: String,
-    #[serde(with = "ts_seconds")]
-    pub timezone: DateTime<Utc>,
+    #[serde(default)]
+    pub utc_offset: i32,
 }
 
 #[derive(Clone, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
@@ -36,15 +40,39 @@ pub struct SurrealDeclaration {
     pub product_quantity: i64,
     pub product_weight: f64,
     pub product_description: String,
+    #[serde(default)]
+    pub goods: Vec<GoodsItem>,
+    #[serde(default)]
+    pub currency: String,
     pub transport_type: String,
     pub transport_name: String,
+    #[serde(default)]
+    pub procedure: String,
     pub sender_name: String,
     pub receiver_name: String,
     pub destination: String,
     pub departure: String,
+    #[serde(default)]
+    pub registration_region: String,
+    #[serde(default)]
+    pub registration_number: Option<RegistrationNumber>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub decision: Option<Decision>,
+    #[serde(default)]
+    pub info_request: Option<InfoRequest>,
+    #[serde(default)]
+    pub revisions: Vec<Revision>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub signature: Option<Signature>,
     pub state: String,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
+    #[serde(default)]
+    pub submitted_at: Option<chrono::DateTime<Utc>>,
 }
 
 impl DeclarationGeneric {
@@ -63,14 +91,27 @@ impl DeclarationGeneric {
         res.set_product_weight(value.product_weight).await;
         res.set_product_description(&value.product_description)
             .await;
+        res.set_goods(value.goods).await;
+        res.set_currency(&value.currency).await;
         res.set_transport_type(&value.transport_type).await;
         res.set_transport_name(&value.transport_name).await;
+        res.set_procedure(&value.procedure).await;
         res.set_sender_name(&value.sender_name).await;
         res.set_receiver_name(&value.receiver_name).await;
         res.set_destination(&value.destination).await;
         res.set_receiver_name(&value.receiver_name).await;
+        res.set_registration_region(&value.registration_region)
+            .await;
+        res.set_registration_number(value.registration_number).await;
+        res.set_priority(value.priority).await;
+        res.set_decision(value.decision).await;
+        res.set_info_request(value.info_request).await;
+        res.restore_revisions(value.revisions).await;
+        res.set_attachments(value.attachments).await;
+        res.set_signature(value.signature).await;
         res.set_created_at(value.created_at).await;
         res.set_updated_at(value.updated_at).await;
+        res.set_submitted_at(value.submitted_at).await;
 
         let state = value.state;
         match state {
@@ -85,6 +126,11 @@ impl DeclarationGeneric {
                                                                           // away
                 ))
             }
+            state if state == std::any::type_name::<InfoRequested>().to_lowercase() => {
+                let res: Declaration<Pending> = res.into();
+                let res: Declaration<Inspecting> = res.into();
+                Ok(Self::InfoRequested(res.into()))
+            }
             state if state == std::any::type_name::<Approved>().to_lowercase() => {
                 Ok(Self::Approved(
                     (|| -> Declaration<Inspecting> {
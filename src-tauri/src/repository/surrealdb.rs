@@ -17,6 +17,7 @@ use crate::{
         declaration::Declaration,
         misc::location::Location,
         participants::{client, declarant::Declarant, representative::Representative},
+        processor::record::DispatchRecord,
     },
     utils::HasId,
 };
@@ -170,3 +171,4 @@ impl Repository<Customs, Surreal<Client>> for SurrealRepo<Customs> {}
 impl Repository<Location, Surreal<Client>> for SurrealRepo<Location> {}
 impl Repository<Inspector, Surreal<Client>> for SurrealRepo<Inspector> {}
 impl Repository<Operator, Surreal<Client>> for SurrealRepo<Operator> {}
+impl Repository<DispatchRecord, Surreal<Client>> for SurrealRepo<DispatchRecord> {}